@@ -0,0 +1,245 @@
+//! A crate that provides a Worm SQL executor for DuckDB
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+use core::convert::TryFrom;
+use duckdb::params_from_iter;
+use duckdb::types::ToSqlOutput;
+use duckdb::types::Value;
+use duckdb::types::ValueRef;
+use duckdb::Connection;
+use duckdb::Error as DuckDbError;
+use duckdb::Row;
+use duckdb::ToSql;
+use std::sync::Arc;
+use worm::errors::ConnectionError;
+use worm::errors::RawRowConversionError;
+use worm::errors::RowConversionError;
+use worm::errors::SqlExecutionError;
+use worm::executors::ResultSet;
+use worm::executors::SqlExecutor;
+use worm::sql::ColumnDescription;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+use worm::transactions::TransactionOptions;
+
+struct DuckDbWormSqlParam<'a>(&'a SqlValue);
+
+impl<'a> ToSql for DuckDbWormSqlParam<'a> {
+    fn to_sql(&self) -> duckdb::Result<ToSqlOutput<'_>> {
+        let value = match self.0 {
+            SqlValue::Boolean(b) => Value::Boolean(*b),
+            SqlValue::Float32(f) => Value::Float(*f),
+            SqlValue::Float64(f) => Value::Double(*f),
+            SqlValue::Null => Value::Null,
+            SqlValue::String(string) => {
+                return Ok(ToSqlOutput::Borrowed(ValueRef::Text(string.as_bytes())))
+            }
+            SqlValue::Signed8(i) => Value::TinyInt(*i),
+            SqlValue::Signed16(i) => Value::SmallInt(*i),
+            SqlValue::Signed32(i) => Value::Int(*i),
+            SqlValue::Signed64(i) => Value::BigInt(*i),
+            SqlValue::Signed128(i) => Value::HugeInt(*i),
+            SqlValue::Unsigned8(u) => Value::UTinyInt(*u),
+            SqlValue::Unsigned16(u) => Value::USmallInt(*u),
+            SqlValue::Unsigned32(u) => Value::UInt(*u),
+            SqlValue::Unsigned64(u) => Value::UBigInt(*u),
+            SqlValue::Unsigned128(u) => Value::HugeInt(
+                i128::try_from(*u)
+                    .map_err(|err| DuckDbError::ToSqlConversionFailure(err.into()))?,
+            ),
+        };
+
+        Ok(ToSqlOutput::Owned(value))
+    }
+}
+
+/// A worm executor for DuckDB which runs the database in-process.
+///
+/// The connection string is either `:memory:` (optionally prefixed by
+/// `duckdb:`) for a private in-memory database, or the path of a database
+/// file (optionally prefixed by `duckdb://`).
+///
+/// A [SqlValue] only holds a single scalar, so columns of the nested types
+/// (`LIST`, `ARRAY`, `STRUCT` and `MAP`) cannot be read and fail with
+/// [RawRowConversionError::UnsupportedType]. Their elements have no column
+/// names or declared types to convert them by, and flattening them into text
+/// would silently choose a format. Such columns can be unnested or converted
+/// in the SQL instead, for example with `unnest(list)` or `to_json(struct)`.
+pub struct DuckDbExecutor(Connection);
+
+impl SqlExecutor for DuckDbExecutor {
+    fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let path = connection_string
+            .strip_prefix("duckdb://")
+            .or_else(|| connection_string.strip_prefix("duckdb:"))
+            .unwrap_or(connection_string);
+
+        let connection = if path == ":memory:" {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(path)
+        };
+
+        match connection {
+            Ok(connection) => Ok(Self(connection)),
+            Err(_) => Err(ConnectionError::new(connection_string.to_owned())),
+        }
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        let mut statement = self.0.prepare(sql).map_err(execution_error)?;
+
+        // The rows borrow the statement, so they are read before returning.
+        let duckdb_params = params_from_iter(params.iter().map(DuckDbWormSqlParam));
+        let mut duckdb_rows = statement.query(duckdb_params).map_err(execution_error)?;
+
+        // The columns of a statement are only known once it has run.
+        let description: Arc<[ColumnDescription]> = duckdb_rows
+            .as_ref()
+            .map(|statement| {
+                statement
+                    .column_names()
+                    .iter()
+                    .map(|name| ColumnDescription::new(name))
+                    .collect()
+            })
+            .unwrap_or_else(|| Arc::from(Vec::new()));
+        let mut columns = None;
+        let mut rows = vec![];
+        loop {
+            match duckdb_rows.next() {
+                Ok(Some(duckdb_row)) => rows.push(convert_row(duckdb_row, &mut columns)),
+                Ok(None) => break,
+                Err(err) => {
+                    rows.push(Err(RawRowConversionError::Other { err: err.into() }.into()));
+                    break;
+                }
+            }
+        }
+
+        Ok(ResultSet::new(description, Box::new(rows.into_iter())))
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let duckdb_params = params_from_iter(params.iter().map(DuckDbWormSqlParam));
+        self.0
+            .execute(sql, duckdb_params)
+            .map(|affected| affected as u64)
+            .map_err(execution_error)
+    }
+
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        self.0.execute_batch(sql).map_err(execution_error)
+    }
+
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        // DuckDB transactions always use snapshot isolation, which none of the
+        // standard isolation levels names, and can neither be read only nor
+        // deferrable, so asking for any of them is an error rather than being
+        // silently ignored.
+        let unsupported = options
+            .get_isolation_level()
+            .map(|isolation_level| format!("the isolation level {}", isolation_level))
+            .or_else(|| match options.get_read_only() {
+                Some(true) => Some(String::from("read only transactions")),
+                _ => None,
+            })
+            .or_else(|| match options.get_deferrable() {
+                Some(true) => Some(String::from("deferrable transactions")),
+                _ => None,
+            });
+
+        if let Some(unsupported) = unsupported {
+            let message = format!("DuckDB does not support {}", unsupported);
+            return Err(SqlExecutionError::Other {
+                err: message.into(),
+            });
+        }
+
+        self.0
+            .execute_batch("BEGIN TRANSACTION")
+            .map_err(execution_error)
+    }
+
+    fn create_savepoint(&mut self, _: &str) -> Result<(), SqlExecutionError> {
+        Err(SqlExecutionError::Other {
+            err: "DuckDB does not support savepoints, so transactions cannot be nested".into(),
+        })
+    }
+}
+
+fn execution_error(err: DuckDbError) -> SqlExecutionError {
+    SqlExecutionError::Other { err: Box::new(err) }
+}
+
+fn convert_row(
+    duckdb_row: &Row,
+    columns: &mut Option<Arc<[String]>>,
+) -> Result<SqlRow, RowConversionError> {
+    // Every row of a result set shares the column names of the first row.
+    let columns = columns
+        .get_or_insert_with(|| duckdb_row.as_ref().column_names().into())
+        .clone();
+
+    let values = (0..columns.len())
+        .map(|i| {
+            let value = duckdb_row
+                .get::<_, Value>(i)
+                .map_err(|err| RawRowConversionError::Other { err: err.into() })?;
+
+            convert_value(&columns[i], value)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(SqlRow::new(columns, values))
+}
+
+fn convert_value(column_name: &str, value: Value) -> Result<SqlValue, RawRowConversionError> {
+    let type_name = match value {
+        Value::Null => return Ok(SqlValue::Null),
+        Value::Boolean(b) => return Ok(SqlValue::Boolean(b)),
+        Value::TinyInt(i) => return Ok(SqlValue::Signed8(i)),
+        Value::SmallInt(i) => return Ok(SqlValue::Signed16(i)),
+        Value::Int(i) => return Ok(SqlValue::Signed32(i)),
+        Value::BigInt(i) => return Ok(SqlValue::Signed64(i)),
+        Value::HugeInt(i) => return Ok(SqlValue::Signed128(i)),
+        Value::UTinyInt(u) => return Ok(SqlValue::Unsigned8(u)),
+        Value::USmallInt(u) => return Ok(SqlValue::Unsigned16(u)),
+        Value::UInt(u) => return Ok(SqlValue::Unsigned32(u)),
+        Value::UBigInt(u) => return Ok(SqlValue::Unsigned64(u)),
+        Value::Float(f) => return Ok(SqlValue::Float32(f)),
+        Value::Double(f) => return Ok(SqlValue::Float64(f)),
+        // Decimals are kept as text so that no precision is lost.
+        Value::Decimal(d) => return Ok(SqlValue::String(d.to_string())),
+        Value::Text(string) | Value::Enum(string) => return Ok(SqlValue::String(string)),
+        // SqlValue has no equivalent of the temporal types, nor of the nested
+        // types since it only holds scalars (see DuckDbExecutor).
+        Value::List(_) => Some("LIST"),
+        Value::Array(_) => Some("ARRAY"),
+        Value::Struct(_) => Some("STRUCT"),
+        Value::Map(_) => Some("MAP"),
+        Value::Blob(_) => Some("BLOB"),
+        Value::Date32(_) => Some("DATE"),
+        Value::Time64(..) => Some("TIME"),
+        Value::Timestamp(..) => Some("TIMESTAMP"),
+        _ => None,
+    };
+
+    Err(RawRowConversionError::UnsupportedType {
+        column_name: column_name.to_owned(),
+        type_name: type_name.map(String::from),
+    })
+}
@@ -0,0 +1,136 @@
+//! Smoke tests which run against an in-memory DuckDB database, so they need
+//! no server and run with the other tests.
+
+use worm::connections::Connection;
+use worm::errors::RawRowConversionError;
+use worm::errors::RowConversionError;
+use worm::errors::WormError;
+use worm::executors::SqlExecutor;
+use worm::sql::SqlValue;
+use worm::testing::query;
+use worm::testing::run;
+use worm::transactions::IsolationLevel;
+use worm::transactions::TransactionOptions;
+use worm_duckdb::DuckDbExecutor;
+
+fn connect() -> Connection<DuckDbExecutor> {
+    let executor = DuckDbExecutor::connect(":memory:").expect("unable to open DuckDB");
+    Connection::new(executor)
+}
+
+#[test]
+fn maps_scalar_types() {
+    let mut connection = connect();
+    let rows = query(
+        &mut connection,
+        "SELECT true AS b, 1::TINYINT AS t, 2::INTEGER AS i, 3::UBIGINT AS u,
+            1.5::DOUBLE AS d, 1.25::DECIMAL(4, 2) AS m, 'text' AS s, NULL AS n",
+        &[],
+    );
+
+    let row = &rows[0];
+    assert_eq!(row.columns(), ["b", "t", "i", "u", "d", "m", "s", "n"]);
+    let values = row.iter().cloned().collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            SqlValue::Boolean(true),
+            SqlValue::Signed8(1),
+            SqlValue::Signed32(2),
+            SqlValue::Unsigned64(3),
+            SqlValue::Float64(1.5),
+            SqlValue::String("1.25".into()),
+            SqlValue::String("text".into()),
+            SqlValue::Null,
+        ]
+    );
+}
+
+#[test]
+fn binds_parameters_and_counts_affected_rows() {
+    let mut connection = connect();
+    run(
+        &mut connection,
+        "CREATE TABLE users (id BIGINT, name VARCHAR)",
+        &[],
+    );
+
+    let affected = connection
+        .executor_mut()
+        .execute_sql_affected(
+            "INSERT INTO users VALUES ($1, $2), ($3, $4)",
+            &[
+                SqlValue::Signed64(1),
+                SqlValue::String("first".into()),
+                SqlValue::Signed64(2),
+                SqlValue::String("second".into()),
+            ],
+        )
+        .unwrap();
+    assert_eq!(affected, 2);
+
+    let rows = query(
+        &mut connection,
+        "SELECT name FROM users WHERE id = $1",
+        &[SqlValue::Signed64(2)],
+    );
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].try_get::<String, _>("name").unwrap(), "second");
+}
+
+#[test]
+fn rejects_nested_types() {
+    let mut connection = connect();
+    for sql in &["SELECT [1, 2] AS l", "SELECT {'a': 1} AS s"] {
+        let mut rows = connection.query_dynamic(sql, &[]).unwrap();
+        match rows.next() {
+            Some(Err(RowConversionError::RawRowConversionFailure(
+                RawRowConversionError::UnsupportedType { .. },
+            ))) => {}
+            other => panic!("{} returned {:?}", sql, other),
+        }
+    }
+
+    // The nested values can still be read once they are converted in SQL.
+    let rows = query(
+        &mut connection,
+        "SELECT to_json({'a': 1})::VARCHAR AS s",
+        &[],
+    );
+    assert_eq!(rows[0].try_get::<String, _>("s").unwrap(), r#"{"a":1}"#);
+}
+
+#[test]
+fn rolls_back_transactions() {
+    let mut connection = connect();
+    run(&mut connection, "CREATE TABLE users (name VARCHAR)", &[]);
+
+    let result: Result<(), WormError> = connection.transaction(|tx| {
+        run(tx, "INSERT INTO users VALUES ('discarded')", &[]);
+        Err(worm::errors::CardinalityError::NoRows.into())
+    });
+    assert!(result.is_err());
+
+    let rows = query(&mut connection, "SELECT name FROM users", &[]);
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn rejects_unsupported_transaction_options() {
+    let mut connection = connect();
+    let unsupported = [
+        TransactionOptions::new().isolation_level(IsolationLevel::ReadCommitted),
+        TransactionOptions::new().isolation_level(IsolationLevel::Serializable),
+        TransactionOptions::new().read_only(true),
+        TransactionOptions::new().deferrable(true),
+    ];
+
+    for options in &unsupported {
+        let result: Result<(), WormError> = connection.transaction_with(options, |_| Ok(()));
+        assert!(result.is_err(), "{:?} was accepted", options);
+    }
+
+    let read_write = TransactionOptions::new().read_only(false).deferrable(false);
+    let result: Result<(), WormError> = connection.transaction_with(&read_write, |_| Ok(()));
+    result.unwrap();
+}
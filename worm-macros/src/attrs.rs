@@ -1,6 +1,8 @@
 use attribution::AttrArgs;
 use proc_macro2::Ident;
 use proc_macro2::Span;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
 use std::path::PathBuf;
 use syn::punctuated::Punctuated;
 use syn::token::Paren;
@@ -12,11 +14,23 @@ use syn::TypeTuple;
 struct WormAttr {
     path: Option<String>,
     result: Option<String>,
+    returns: Option<String>,
+    timeout: Option<String>,
+}
+
+#[derive(AttrArgs)]
+struct TestAttr {
+    isolation: Option<String>,
+    template: Option<String>,
+    migrations: Option<String>,
+    env: Option<String>,
 }
 
 pub struct WormScriptConfig {
     path: PathBuf,
     result: Type,
+    returns: Option<Ident>,
+    timeout: Option<u64>,
 }
 
 impl WormScriptConfig {
@@ -27,6 +41,14 @@ impl WormScriptConfig {
     pub fn result(&self) -> &Type {
         &self.result
     }
+
+    pub fn returns(&self) -> Option<&Ident> {
+        self.returns.as_ref()
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
 }
 
 pub fn get_helper_attr<'a>(
@@ -43,12 +65,27 @@ pub fn get_helper_attr<'a>(
             .result
             .map(build_result_type)
             .unwrap_or_else(|| Ok(default_result_type()))?;
+        let returns = attr
+            .returns
+            .map(|returns| build_returns_variant(type_name, returns))
+            .transpose()?;
+        let timeout = attr
+            .timeout
+            .map(|timeout| parse_timeout_millis(type_name, timeout))
+            .transpose()?;
 
-        Ok(WormScriptConfig { path, result })
+        Ok(WormScriptConfig {
+            path,
+            result,
+            returns,
+            timeout,
+        })
     } else {
         Ok(WormScriptConfig {
             path: default_script_path(type_name),
             result: default_result_type(),
+            returns: None,
+            timeout: None,
         })
     }
 }
@@ -81,3 +118,183 @@ fn default_result_type() -> Type {
 fn build_result_type(type_name: impl AsRef<str>) -> Result<Type, syn::Error> {
     syn::parse_str(type_name.as_ref())
 }
+
+fn build_returns_variant(type_name: &Ident, returns: impl AsRef<str>) -> Result<Ident, syn::Error> {
+    let variant = match returns.as_ref() {
+        "none" => "None",
+        "one" => "One",
+        "optional" => "Optional",
+        "many" => "Many",
+        other => {
+            let message = format!(
+                "'{}' is not a valid value for 'returns'. Expected one of 'none', 'one', 'optional' or 'many'.",
+                other
+            );
+            return Err(syn::Error::new_spanned(type_name, message));
+        }
+    };
+
+    Ok(Ident::new(variant, Span::call_site()))
+}
+
+/// Parses a timeout like `500ms`, `5s`, `2m` or `1h` into milliseconds. The
+/// timeout must be at least a millisecond and at most `i32::MAX`
+/// milliseconds, which is the longest timeout databases such as PostgreSQL
+/// accept.
+fn parse_timeout_millis(type_name: &Ident, timeout: impl AsRef<str>) -> Result<u64, syn::Error> {
+    let timeout = timeout.as_ref().trim();
+    let unit_start = timeout
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(timeout.len());
+    let (amount, unit) = timeout.split_at(unit_start);
+
+    let millis_per_unit = match unit.trim() {
+        "ms" => Some(1),
+        "s" => Some(1_000),
+        "m" => Some(60_000),
+        "h" => Some(3_600_000),
+        _ => None,
+    };
+
+    let millis = match (amount.parse::<u64>(), millis_per_unit) {
+        (Ok(amount), Some(millis_per_unit)) => amount.checked_mul(millis_per_unit),
+        _ => {
+            let message = format!(
+                "'{}' is not a valid value for 'timeout'. Expected a whole number followed by one of 'ms', 's', 'm' or 'h'.",
+                timeout
+            );
+            return Err(syn::Error::new_spanned(type_name, message));
+        }
+    };
+
+    match millis {
+        Some(0) => {
+            let message = format!(
+                "'{}' is not a valid value for 'timeout'. The timeout must be at least 1ms.",
+                timeout
+            );
+            Err(syn::Error::new_spanned(type_name, message))
+        }
+        Some(millis) if millis <= i32::MAX as u64 => Ok(millis),
+        _ => {
+            let message = format!(
+                "'{}' is not a valid value for 'timeout'. The timeout must be at most {}ms.",
+                timeout,
+                i32::MAX
+            );
+            Err(syn::Error::new_spanned(type_name, message))
+        }
+    }
+}
+
+pub struct WormTestConfig {
+    isolation: Option<Ident>,
+    template: Option<String>,
+    migrations: Option<String>,
+    env: Option<String>,
+}
+
+impl WormTestConfig {
+    pub fn isolation(&self) -> Option<&Ident> {
+        self.isolation.as_ref()
+    }
+
+    pub fn template(&self) -> Option<&String> {
+        self.template.as_ref()
+    }
+
+    pub fn migrations(&self) -> Option<&String> {
+        self.migrations.as_ref()
+    }
+
+    pub fn env(&self) -> Option<&String> {
+        self.env.as_ref()
+    }
+}
+
+pub fn get_test_args(test_name: &Ident, args: TokenStream2) -> Result<WormTestConfig, syn::Error> {
+    if args.is_empty() {
+        return Ok(WormTestConfig {
+            isolation: None,
+            template: None,
+            migrations: None,
+            env: None,
+        });
+    }
+
+    let attr = syn::parse2::<TestAttr>(quote! { (#args) })?;
+    let isolation = attr
+        .isolation
+        .map(|isolation| build_isolation_variant(test_name, isolation))
+        .transpose()?;
+
+    Ok(WormTestConfig {
+        isolation,
+        template: attr.template,
+        migrations: attr.migrations,
+        env: attr.env,
+    })
+}
+
+fn build_isolation_variant(
+    test_name: &Ident,
+    isolation: impl AsRef<str>,
+) -> Result<Ident, syn::Error> {
+    let variant = match isolation.as_ref() {
+        "transaction" => "Transaction",
+        "schema" => "Schema",
+        "database" => "Database",
+        other => {
+            let message = format!(
+                "'{}' is not a valid value for 'isolation'. Expected one of 'transaction', 'schema' or 'database'.",
+                other
+            );
+            return Err(syn::Error::new_spanned(test_name, message));
+        }
+    };
+
+    Ok(Ident::new(variant, Span::call_site()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_timeout_millis;
+    use proc_macro2::Span;
+    use syn::Ident;
+
+    fn parse(timeout: &str) -> Result<u64, String> {
+        let type_name = Ident::new("FindUsers", Span::call_site());
+        parse_timeout_millis(&type_name, timeout).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parses_every_unit() {
+        assert_eq!(parse("500ms"), Ok(500));
+        assert_eq!(parse("5s"), Ok(5_000));
+        assert_eq!(parse(" 2 m "), Ok(120_000));
+        assert_eq!(parse("1h"), Ok(3_600_000));
+    }
+
+    #[test]
+    fn rejects_malformed_timeouts() {
+        for timeout in &["", "5", "ms", "-5s", "1.5s", "5d"] {
+            let err = parse(timeout).unwrap_err();
+            assert!(err.contains("Expected a whole number"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn rejects_a_timeout_of_zero() {
+        let err = parse("0s").unwrap_err();
+        assert!(err.contains("at least 1ms"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_timeouts_which_are_too_long() {
+        assert_eq!(parse("2147483647ms"), Ok(i32::MAX as u64));
+        for timeout in &["2147483648ms", "597h", "18446744073709551615h"] {
+            let err = parse(timeout).unwrap_err();
+            assert!(err.contains("at most 2147483647ms"), "{}", err);
+        }
+    }
+}
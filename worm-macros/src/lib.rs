@@ -3,6 +3,7 @@
 mod attrs;
 
 use self::attrs::get_helper_attr;
+use self::attrs::get_test_args;
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use proc_macro2::TokenStream as TokenStream2;
@@ -15,6 +16,9 @@ use syn::Data;
 use syn::DataStruct;
 use syn::DeriveInput;
 use syn::Field;
+use syn::Fields;
+use syn::FieldsNamed;
+use syn::ItemFn;
 
 #[proc_macro_derive(Script, attributes(worm))]
 pub fn derive_script(tagged: TokenStream) -> TokenStream {
@@ -41,8 +45,19 @@ fn impl_derive_script_struct(
     type_name: &Ident,
     tagged_struct: &DataStruct,
 ) -> Result<TokenStream2, syn::Error> {
-    let attr = get_helper_attr(&type_name, attrs.iter())?;
+    let attr = get_helper_attr(type_name, attrs.iter())?;
     let return_type = attr.result();
+    let returns = attr.returns().map(|variant| {
+        quote! {
+            const RETURNS: ::worm::Returns = ::worm::Returns::#variant;
+        }
+    });
+    let timeout = attr.timeout().map(|millis| {
+        quote! {
+            const TIMEOUT: ::core::option::Option<::core::time::Duration> =
+                ::core::option::Option::Some(::core::time::Duration::from_millis(#millis));
+        }
+    });
     let script_path: &PathBuf = attr.path();
 
     // Load the script from the file system.
@@ -60,6 +75,8 @@ fn impl_derive_script_struct(
     Ok(quote! {
         impl ::worm::Script for #type_name {
             type Output = #return_type;
+            #returns
+            #timeout
             fn compile(self) -> ::std::string::String {
                 #sql_template
             }
@@ -86,7 +103,7 @@ fn build_sql(
                 .filter(|(_, c)| !c.is_alphabetic())
                 .map(|(i, _)| i + start + 1)
                 .next()
-                .unwrap_or_else(|| script.len());
+                .unwrap_or(script.len());
 
             (start, end)
         });
@@ -105,8 +122,8 @@ fn build_sql(
 
         // Build the ident for the struct
         let bare_param = &param[1..];
-        let field = find_field(&tagged_struct, bare_param)
-            .map(|field| Ident::new(bare_param, field.span()));
+        let field =
+            find_field(tagged_struct, bare_param).map(|field| Ident::new(bare_param, field.span()));
 
         // Add the value for the
         if let Some(ident) = field {
@@ -118,8 +135,7 @@ fn build_sql(
         } else {
             let message = format!(
                 "The type '{}' has no field with the name '{}'",
-                type_name.to_string(),
-                bare_param
+                type_name, bare_param
             );
             let error = syn::Error::new_spanned(&tagged_struct.fields, message);
             return Err(error);
@@ -140,6 +156,62 @@ fn find_field<'a>(tagged_struct: &'a DataStruct, name: &str) -> Option<&'a Field
     })
 }
 
+/// Turns a function which takes a `&mut Connection<E>` into a test which is
+/// run against an isolated database through `worm::testing::TestDatabase`.
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, tagged: TokenStream) -> TokenStream {
+    let tagged = parse_macro_input!(tagged as ItemFn);
+    impl_test(args.into(), tagged)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn impl_test(args: TokenStream2, tagged: ItemFn) -> Result<TokenStream2, syn::Error> {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = tagged;
+
+    if sig.inputs.len() != 1 || sig.asyncness.is_some() || !sig.generics.params.is_empty() {
+        let message = "A worm test must be a synchronous, non-generic function which takes a single `&mut Connection<E>`.";
+        return Err(syn::Error::new_spanned(sig, message));
+    }
+
+    let test_name = &sig.ident;
+    let output = &sig.output;
+    let config = get_test_args(test_name, args)?;
+    let isolation = config.isolation().map(|variant| {
+        quote! { .isolation(::worm::testing::Isolation::#variant) }
+    });
+    let template = config
+        .template()
+        .map(|template| quote! { .template(#template) });
+    // Migrations are found relative to the crate which contains the test.
+    let migrations = config.migrations().map(|migrations| {
+        quote! {
+            .migrations(::std::path::Path::new(::core::env!("CARGO_MANIFEST_DIR")).join(#migrations))
+        }
+    });
+    let env = config.env().map(|env| quote! { .url_variable(#env) });
+
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #test_name() #output {
+            #sig #block
+
+            ::worm::testing::TestDatabase::new()
+                #isolation
+                #template
+                #migrations
+                #env
+                .run(#test_name)
+        }
+    })
+}
+
 #[proc_macro_derive(SqlResult)]
 pub fn derive_script_result(tagged: TokenStream) -> TokenStream {
     let tagged = parse_macro_input!(tagged as DeriveInput);
@@ -147,47 +219,52 @@ pub fn derive_script_result(tagged: TokenStream) -> TokenStream {
 }
 
 fn impl_derive_script_result(tagged: DeriveInput) -> TokenStream2 {
-    if let Data::Struct(tagged_struct) = tagged.data {
-        let type_name = &tagged.ident;
-        impl_derive_script_result_struct(type_name, &tagged_struct)
-    } else {
-        let message = "SqlResult can only be derived for a struct";
-        let error = syn::Error::new_spanned(tagged, message);
-        error.to_compile_error()
-    }
-}
-
-fn impl_derive_script_result_struct(type_name: &Ident, tagged_struct: &DataStruct) -> TokenStream2 {
-    let field_extractors = build_row_field_extractors(tagged_struct);
+    match &tagged.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => {
+            let type_name = &tagged.ident;
+            let (impl_generics, type_generics, where_clause) = tagged.generics.split_for_impl();
+            let field_extractors = build_row_field_extractors(fields);
+            let idents = fields.named.iter().map(build_ident_for_field);
 
-    quote! {
-        impl ::worm::sql::SqlResult for #type_name {
-            fn from_row(row: ::worm::sql::SqlRow) -> ::core::result::Result<Self, ::worm::errors::RowConversionError> {
-                let mut values = row.into_iter();
-                use ::worm::sql::RecordField;
+            quote! {
+                impl #impl_generics ::worm::sql::SqlResult for #type_name #type_generics #where_clause {
+                    #[allow(unused_mut, unused_variables)]
+                    fn from_row(mut row: ::worm::sql::SqlRow) -> ::core::result::Result<Self, ::worm::errors::RowConversionError> {
+                        // Rows without column names are matched to the fields
+                        // by position instead.
+                        let by_name = !row.columns().is_empty();
 
-                #(#field_extractors)*
+                        #(#field_extractors)*
 
-                todo!()
+                        Ok(Self { #(#idents),* })
+                    }
+                }
             }
         }
+        _ => {
+            let message = "SqlResult can only be derived for a struct with named fields";
+            let error = syn::Error::new_spanned(tagged, message);
+            error.to_compile_error()
+        }
     }
 }
 
-fn build_row_field_extractors(
-    tagged_struct: &DataStruct,
-) -> impl Iterator<Item = TokenStream2> + '_ {
-    tagged_struct.fields.iter().map(|field| {
+fn build_row_field_extractors(fields: &FieldsNamed) -> impl Iterator<Item = TokenStream2> + '_ {
+    fields.named.iter().enumerate().map(|(index, field)| {
         let ident = build_ident_for_field(field);
+        let column = ident.to_string().trim_start_matches("r#").to_owned();
         quote! {
-            let #ident = match values.next() {
-                Some(value) => String::from_sql(value)?,
-                None => {
-                    return Err(::worm::errors::RowConversionError::MissingFieldValue {
-                        field_name: "handle",
-                    })
-                }
+            let value = if by_name {
+                row.take(#column)?
+            } else {
+                row.take(#index).map_err(|_| ::worm::errors::RowConversionError::MissingFieldValue {
+                    field_name: #column,
+                })?
             };
+            let #ident = ::worm::sql::RecordField::from_sql(value)?;
         }
     })
 }
@@ -195,3 +272,149 @@ fn build_row_field_extractors(
 fn build_ident_for_field(field: &Field) -> &Ident {
     field.ident.as_ref().unwrap()
 }
+
+#[proc_macro_derive(SqlRecord)]
+pub fn derive_sql_record(tagged: TokenStream) -> TokenStream {
+    let tagged = parse_macro_input!(tagged as DeriveInput);
+    impl_derive_sql_record(tagged).into()
+}
+
+fn impl_derive_sql_record(tagged: DeriveInput) -> TokenStream2 {
+    match &tagged.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => {
+            let type_name = &tagged.ident;
+            let (impl_generics, type_generics, where_clause) = tagged.generics.split_for_impl();
+
+            // Raw identifiers name the column without their prefix.
+            let columns = fields.named.iter().map(|field| {
+                let name = build_ident_for_field(field).to_string();
+                name.trim_start_matches("r#").to_owned()
+            });
+            let values = fields.named.iter().map(|field| {
+                let ident = build_ident_for_field(field);
+                quote! {
+                    ::worm::sql::RecordField::into_sql(self.#ident)
+                }
+            });
+
+            quote! {
+                impl #impl_generics ::worm::sql::SqlRecord for #type_name #type_generics #where_clause {
+                    fn columns() -> &'static [&'static str] {
+                        &[#(#columns),*]
+                    }
+
+                    fn into_values(self) -> ::std::vec::Vec<::worm::sql::SqlValue> {
+                        vec![#(#values),*]
+                    }
+                }
+            }
+        }
+        _ => {
+            let message = "SqlRecord can only be derived for a struct with named fields";
+            let error = syn::Error::new_spanned(tagged, message);
+            error.to_compile_error()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::impl_test;
+    use quote::quote;
+    use syn::ItemFn;
+
+    fn expand_test(args: proc_macro2::TokenStream, tagged: ItemFn) -> Result<String, String> {
+        impl_test(args, tagged)
+            .map(|tokens| tokens.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn expands_to_a_test_which_runs_against_a_test_database() {
+        let tagged = syn::parse_quote! {
+            fn finds_users(connection: &mut Connection<PostgresExecutor>) {
+                connection.fetch_all(FindUsers).unwrap();
+            }
+        };
+
+        let expected = quote! {
+            #[test]
+            fn finds_users() {
+                fn finds_users(connection: &mut Connection<PostgresExecutor>) {
+                    connection.fetch_all(FindUsers).unwrap();
+                }
+
+                ::worm::testing::TestDatabase::new().run(finds_users)
+            }
+        };
+
+        assert_eq!(expand_test(quote! {}, tagged), Ok(expected.to_string()));
+    }
+
+    #[test]
+    fn expands_the_arguments_to_options_of_the_test_database() {
+        let tagged = syn::parse_quote! {
+            #[ignore]
+            pub fn finds_users(connection: &mut Connection<PostgresExecutor>) -> Result<(), WormError> {
+                Ok(())
+            }
+        };
+
+        let args = quote! {
+            isolation = "schema",
+            template = "worm_template",
+            migrations = "migrations",
+            env = "WORM_TEST_URL"
+        };
+
+        let expected = quote! {
+            #[test]
+            #[ignore]
+            pub fn finds_users() -> Result<(), WormError> {
+                fn finds_users(connection: &mut Connection<PostgresExecutor>) -> Result<(), WormError> {
+                    Ok(())
+                }
+
+                ::worm::testing::TestDatabase::new()
+                    .isolation(::worm::testing::Isolation::Schema)
+                    .template("worm_template")
+                    .migrations(::std::path::Path::new(::core::env!("CARGO_MANIFEST_DIR")).join("migrations"))
+                    .url_variable("WORM_TEST_URL")
+                    .run(finds_users)
+            }
+        };
+
+        assert_eq!(expand_test(args, tagged), Ok(expected.to_string()));
+    }
+
+    #[test]
+    fn rejects_an_unknown_isolation() {
+        let tagged = syn::parse_quote! {
+            fn finds_users(connection: &mut Connection<PostgresExecutor>) {}
+        };
+
+        let err = expand_test(quote! { isolation = "process" }, tagged).unwrap_err();
+        assert!(
+            err.contains("'process' is not a valid value for 'isolation'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_functions_which_do_not_take_a_connection() {
+        let tagged_fns: Vec<ItemFn> = vec![
+            syn::parse_quote! { fn no_connection() {} },
+            syn::parse_quote! { async fn asynchronous(connection: &mut Connection<E>) {} },
+            syn::parse_quote! { fn generic<E>(connection: &mut Connection<E>) {} },
+        ];
+
+        for tagged in tagged_fns {
+            let err = expand_test(quote! {}, tagged).unwrap_err();
+            assert!(err.starts_with("A worm test must be"), "{}", err);
+        }
+    }
+}
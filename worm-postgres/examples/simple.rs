@@ -1,7 +1,7 @@
 use std::error::Error;
-use worm::executors::SqlExecutor;
 use worm::sql::SqlResult;
 use worm::Script;
+use worm_postgres::ConnectOptions;
 use worm_postgres::PostgresExecutor;
 
 #[derive(Debug, SqlResult)]
@@ -19,11 +19,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let db_password = std::env::var("DB_PASSWORD")?;
     let db_name = std::env::var("DB_NAME")?;
-    let connection_string = format!(
-        "postgres://agora_admin:{}@localhost:5432/{}",
-        db_password, db_name
-    );
-    let executor = PostgresExecutor::connect(&connection_string)?;
+    let options = ConnectOptions::new()
+        .host("localhost")
+        .port(5432)
+        .user("agora_admin")
+        .password(&db_password)
+        .dbname(&db_name);
+    let executor = PostgresExecutor::connect_with(&options)?;
     let mut connection = worm::connections::Connection::new(executor);
 
     println!("Attempting to run: {}", script.clone().compile());
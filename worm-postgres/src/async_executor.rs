@@ -0,0 +1,103 @@
+use crate::convert_row;
+use crate::tls;
+use crate::ConnectOptions;
+use crate::PgWormSqlParam;
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use futures_util::stream::StreamExt;
+use tokio_postgres::Client;
+use worm::errors::ConnectionError;
+use worm::errors::SqlExecutionError;
+use worm::executors::AsyncSqlExecutor;
+use worm::executors::ResultStream;
+use worm::sql::SqlValue;
+
+/// An asynchronous worm executor for postgres built on tokio-postgres. The
+/// connection is driven by a task spawned onto the current tokio runtime.
+pub struct AsyncPostgresExecutor(Client);
+
+impl AsyncPostgresExecutor {
+    /// Connects to the database described by the options.
+    pub async fn connect_with(options: &ConnectOptions) -> Result<Self, ConnectionError> {
+        tls::connect_async(options)
+            .await
+            .map(Self)
+            .map_err(|err| ConnectionError::with_source(options.to_string(), err))
+    }
+}
+
+#[async_trait]
+impl AsyncSqlExecutor for AsyncPostgresExecutor {
+    async fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let options = ConnectOptions::parse(connection_string)?;
+        Self::connect_with(&options).await
+    }
+
+    async fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultStream<'a>, SqlExecutionError> {
+        let pg_params = params.iter().map(PgWormSqlParam);
+        let pg_row_stream = match self.0.query_raw(sql, pg_params).await {
+            Ok(row_stream) => row_stream,
+            Err(err) => return Err(SqlExecutionError::Other { err: Box::new(err) }),
+        };
+
+        let mut columns = None;
+        let worm_row_stream = pg_row_stream.map(move |pg_row| convert_row(pg_row, &mut columns));
+        Ok(Box::pin(worm_row_stream))
+    }
+
+    async fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let pg_params = params.iter().map(PgWormSqlParam);
+        self.0
+            .execute_raw(sql, pg_params)
+            .await
+            .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })
+    }
+
+    async fn execute_sql_batch(
+        &mut self,
+        statements: &[String],
+    ) -> Vec<Result<u64, SqlExecutionError>> {
+        // Polling the statements together pipelines them over the connection,
+        // so the batch only waits for the server once.
+        let client = &self.0;
+        let executions = statements
+            .iter()
+            .map(|sql| client.execute(sql.as_str(), &[]));
+
+        join_all(executions)
+            .await
+            .into_iter()
+            .map(|result| result.map_err(|err| SqlExecutionError::Other { err: Box::new(err) }))
+            .collect()
+    }
+
+    async fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        // The statements are pipelined like any other batch. Once one of them
+        // fails the transaction is aborted, so the server rejects the rest and
+        // the first error is the one worth reporting.
+        let client = &self.0;
+        let executions = statements
+            .iter()
+            .map(|sql| client.execute(sql.as_str(), &[]));
+
+        join_all(executions)
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })
+    }
+}
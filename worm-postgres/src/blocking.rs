@@ -0,0 +1,127 @@
+use futures_util::future::poll_fn;
+use futures_util::stream::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::task::Context;
+use std::task::Poll;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::runtime::Runtime;
+use tokio_postgres::AsyncMessage;
+use tokio_postgres::Error;
+use tokio_postgres::Notification;
+
+type MessagePoller =
+    Box<dyn FnMut(&mut Context<'_>) -> Poll<Option<Result<AsyncMessage, Error>>> + Send>;
+
+/// The connection of a tokio-postgres client, which is driven on a runtime of
+/// its own while the client waits for the server. Like the `postgres` crate,
+/// this lets a synchronous executor use the asynchronous client, which sends
+/// the statements of a batch without waiting for each response.
+pub(crate) struct BlockingConnection {
+    runtime: Runtime,
+    poll_message: MessagePoller,
+    closed: bool,
+    notifications: VecDeque<Notification>,
+}
+
+impl BlockingConnection {
+    pub fn new<S, T>(runtime: Runtime, mut connection: tokio_postgres::Connection<S, T>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        Self {
+            runtime,
+            poll_message: Box::new(move |cx| connection.poll_message(cx)),
+            closed: false,
+            notifications: VecDeque::new(),
+        }
+    }
+
+    /// The runtime the connection is driven on.
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Drives the connection until the future completes.
+    pub fn block_on<F, T>(&mut self, future: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        let mut future = Box::pin(future);
+        self.poll_block_on(|cx, _, _| future.as_mut().poll(cx))
+    }
+
+    /// Drives the connection until `f` is ready. `f` is also passed the
+    /// notifications which have been received and whether the connection has
+    /// closed.
+    pub fn poll_block_on<F, T>(&mut self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut(&mut Context<'_>, &mut VecDeque<Notification>, bool) -> Poll<Result<T, Error>>,
+    {
+        let poll_message = &mut self.poll_message;
+        let closed = &mut self.closed;
+        let notifications = &mut self.notifications;
+        self.runtime.block_on(poll_fn(|cx| {
+            // Messages are read until the connection is waiting on the
+            // server, which also sends the requests the client has queued.
+            while !*closed {
+                match poll_message(cx) {
+                    Poll::Ready(Some(Ok(AsyncMessage::Notification(notification)))) => {
+                        notifications.push_back(notification)
+                    }
+                    Poll::Ready(Some(Ok(_))) => {}
+                    Poll::Ready(Some(Err(err))) => {
+                        *closed = true;
+                        return Poll::Ready(Err(err));
+                    }
+                    Poll::Ready(None) => *closed = true,
+                    Poll::Pending => break,
+                }
+            }
+
+            f(cx, notifications, *closed)
+        }))
+    }
+
+    /// Iterates over the items of the stream, driving the connection while
+    /// waiting for each of them. The iterator ends after the first error.
+    pub fn iter<'a, S, T>(&'a mut self, stream: S) -> impl Iterator<Item = Result<T, Error>> + 'a
+    where
+        S: Stream<Item = Result<T, Error>> + 'a,
+    {
+        let mut stream = Box::pin(stream);
+        let mut ended = false;
+        std::iter::from_fn(move || {
+            if ended {
+                return None;
+            }
+
+            let next = self
+                .poll_block_on(|cx, _, _| stream.as_mut().poll_next(cx).map(Ok))
+                .unwrap_or_else(|err| Some(Err(err)));
+            ended = !matches!(next, Some(Ok(_)));
+            next
+        })
+    }
+
+    /// The notifications which have been received but not yet read.
+    pub fn notifications_mut(&mut self) -> &mut VecDeque<Notification> {
+        &mut self.notifications
+    }
+}
+
+impl Drop for BlockingConnection {
+    fn drop(&mut self) {
+        // Once its client has been dropped, the connection tells the server
+        // that it is closing and then ends.
+        let _ = self.poll_block_on(|_, _, closed| {
+            if closed {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        });
+    }
+}
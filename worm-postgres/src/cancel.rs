@@ -0,0 +1,109 @@
+use crate::tls::clone_connector;
+use crate::tls::MakeTlsConnector;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Builder;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::CancelToken;
+use tokio_postgres::Error as PostgresError;
+use tokio_postgres::NoTls;
+use worm::errors::SqlExecutionError;
+use worm::executors::Cancel;
+
+/// Tracks the timeout set on a connection and whether its running statement
+/// was cancelled, since the server reports both with the same error code.
+pub(crate) struct Interruptions {
+    /// The timeout set on the session, or `None` if it is unknown because
+    /// the transaction that set it was rolled back.
+    timeout: Option<Option<Duration>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Interruptions {
+    pub fn new() -> Self {
+        Self {
+            // Until a timeout is set, the session uses the server's default.
+            timeout: Some(None),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Builds the statement which changes the timeout of the session, if it
+    /// does not already have the timeout.
+    pub fn timeout_sql(&self, timeout: Option<Duration>) -> Option<String> {
+        if self.timeout == Some(timeout) {
+            return None;
+        }
+
+        // A timeout of zero disables the timeout, so shorter timeouts are
+        // rounded up to a millisecond.
+        Some(match timeout {
+            Some(timeout) => format!("SET statement_timeout = {}", timeout.as_millis().max(1)),
+            None => String::from("RESET statement_timeout"),
+        })
+    }
+
+    pub fn timeout_set(&mut self, timeout: Option<Duration>) {
+        self.timeout = Some(timeout);
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Forgets the timeout of the session after the change may have been
+    /// undone by a rollback.
+    pub fn rolled_back(&mut self) {
+        self.timeout = None;
+    }
+
+    pub fn canceller(&self, token: CancelToken, tls: Option<MakeTlsConnector>) -> PgCanceller {
+        PgCanceller {
+            token,
+            tls,
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    /// Converts an error from the server, telling apart statements which
+    /// were cancelled through a handle from those which timed out.
+    pub fn execution_error(&self, err: PostgresError) -> SqlExecutionError {
+        if err.code() != Some(&SqlState::QUERY_CANCELED) {
+            return SqlExecutionError::Other { err: Box::new(err) };
+        }
+
+        if self.cancelled.swap(false, Ordering::SeqCst) {
+            SqlExecutionError::Cancelled
+        } else if let Some(Some(_)) = self.timeout {
+            SqlExecutionError::TimedOut
+        } else {
+            SqlExecutionError::Other { err: Box::new(err) }
+        }
+    }
+}
+
+/// Cancels the statement running on a [PostgresExecutor](crate::PostgresExecutor)
+/// by opening a separate connection to the server, which is encrypted like
+/// the connection of the executor.
+pub(crate) struct PgCanceller {
+    token: CancelToken,
+    tls: Option<MakeTlsConnector>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancel for PgCanceller {
+    fn cancel(&self) -> Result<(), SqlExecutionError> {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // The handle may be used from any thread, so the request is sent on a
+        // runtime of its own rather than the runtime of the executor.
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })?;
+        let result = match &self.tls {
+            Some(tls) => runtime.block_on(self.token.cancel_query(clone_connector(tls))),
+            None => runtime.block_on(self.token.cancel_query(NoTls)),
+        };
+
+        result.map_err(|err| SqlExecutionError::Other { err: Box::new(err) })
+    }
+}
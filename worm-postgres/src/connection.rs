@@ -0,0 +1,120 @@
+use crate::CopyOutOptions;
+use crate::Notifications;
+use crate::PostgresExecutor;
+use std::io::Write;
+use worm::connections::Connection;
+use worm::errors::CardinalityError;
+use worm::errors::WormError;
+use worm::executors::SqlExecutor;
+use worm::results::QueryResults;
+use worm::sql::SqlRecord;
+use worm::sql::SqlResult;
+use worm::Script;
+
+/// Methods for features of PostgreSQL which other DBMSs lack. Transactions
+/// dereference to their connection, so these can be used within them too.
+pub trait PostgresConnectionExt {
+    /// Bulk loads the records into the table, which is much faster than
+    /// inserting them one at a time. The fields of the records name the
+    /// columns they are loaded into. Returns the number of rows loaded.
+    fn copy_in<T, I>(&mut self, table: &str, rows: I) -> Result<u64, WormError>
+    where
+        T: SqlRecord,
+        I: IntoIterator<Item = T>;
+
+    /// Exports the rows returned by the script to `writer` as CSV or TSV. The
+    /// rows are written as they are received rather than collected first.
+    /// Returns the number of bytes written.
+    fn copy_out_to<S, W>(
+        &mut self,
+        script: S,
+        options: &CopyOutOptions,
+        writer: W,
+    ) -> Result<u64, WormError>
+    where
+        S: Script,
+        W: Write;
+
+    /// Exports the rows returned by the script in the binary format of
+    /// `COPY`, decoding each row as it is received.
+    fn copy_out<S>(&mut self, script: S) -> Result<QueryResults<'_, S::Output>, WormError>
+    where
+        S: Script;
+
+    /// Starts listening for notifications sent to the channel.
+    fn listen(&mut self, channel: &str) -> Result<(), WormError>;
+
+    /// Stops listening for notifications sent to the channel.
+    fn unlisten(&mut self, channel: &str) -> Result<(), WormError>;
+
+    /// Sends a notification to every connection listening on the channel.
+    /// Within a transaction, the notification is only sent once it commits.
+    fn notify(&mut self, channel: &str, payload: &str) -> Result<(), WormError>;
+
+    /// The notifications received on the channels the connection listens
+    /// on, blocking until each one arrives.
+    fn notifications(&mut self) -> Notifications<'_>;
+}
+
+impl PostgresConnectionExt for Connection<PostgresExecutor> {
+    fn copy_in<T, I>(&mut self, table: &str, rows: I) -> Result<u64, WormError>
+    where
+        T: SqlRecord,
+        I: IntoIterator<Item = T>,
+    {
+        let timeout = self.timeout();
+        let executor = self.executor_mut();
+        executor.set_timeout(timeout)?;
+        Ok(executor.copy_in(table, rows)?)
+    }
+
+    fn copy_out_to<S, W>(
+        &mut self,
+        script: S,
+        options: &CopyOutOptions,
+        writer: W,
+    ) -> Result<u64, WormError>
+    where
+        S: Script,
+        W: Write,
+    {
+        let timeout = S::TIMEOUT.or_else(|| self.timeout());
+        let sql = script.compile();
+        let executor = self.executor_mut();
+        executor.set_timeout(timeout)?;
+        Ok(executor.copy_out_to(&sql, options, writer)?)
+    }
+
+    fn copy_out<S>(&mut self, script: S) -> Result<QueryResults<'_, S::Output>, WormError>
+    where
+        S: Script,
+    {
+        let expected = <S::Output as SqlResult>::RESULT_SETS;
+        if expected != 1 {
+            return Err(CardinalityError::MultipleResultSets { expected }.into());
+        }
+
+        let timeout = S::TIMEOUT.or_else(|| self.timeout());
+        let sql = script.compile();
+        let executor = self.executor_mut();
+        executor.set_timeout(timeout)?;
+        let row_iter = executor.copy_out_rows(&sql)?;
+        Ok(QueryResults::new(row_iter))
+    }
+
+    fn listen(&mut self, channel: &str) -> Result<(), WormError> {
+        Ok(self.executor_mut().listen(channel)?)
+    }
+
+    fn unlisten(&mut self, channel: &str) -> Result<(), WormError> {
+        Ok(self.executor_mut().unlisten(channel)?)
+    }
+
+    fn notify(&mut self, channel: &str, payload: &str) -> Result<(), WormError> {
+        Ok(self.executor_mut().notify(channel, payload)?)
+    }
+
+    fn notifications(&mut self) -> Notifications<'_> {
+        self.executor_mut().notifications()
+    }
+}
@@ -0,0 +1,502 @@
+use crate::describe_columns;
+use crate::quote_identifier;
+use crate::PgWormSqlValue;
+use crate::PostgresExecutor;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::binary_copy::BinaryCopyOutRow;
+use tokio_postgres::binary_copy::BinaryCopyOutStream;
+use tokio_postgres::types::to_sql_checked;
+use tokio_postgres::types::IsNull;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::types::Type;
+use tokio_postgres::types::WrongType;
+use worm::errors::RawRowConversionError;
+use worm::errors::RowConversionError;
+use worm::errors::SqlExecutionError;
+use worm::executors::ResultSet;
+use worm::sql::split_statements;
+use worm::sql::SqlRecord;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+
+/// The text format a query's rows are exported in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CopyFormat {
+    /// Comma separated values.
+    Csv,
+    /// Tab separated values. Fields are quoted by the rules of CSV when they
+    /// contain a tab, a quote or a line break.
+    Tsv,
+}
+
+/// The options of an export with `COPY ... TO STDOUT`.
+#[derive(Clone, Debug)]
+pub struct CopyOutOptions {
+    format: CopyFormat,
+    header: bool,
+}
+
+impl Default for CopyOutOptions {
+    fn default() -> Self {
+        Self {
+            format: CopyFormat::Csv,
+            header: true,
+        }
+    }
+}
+
+impl CopyOutOptions {
+    /// Creates a set of options which exports CSV with a header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the format the rows are exported in.
+    pub fn format(mut self, format: CopyFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether the first line holds the names of the columns.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Gets the format the rows are exported in.
+    pub fn get_format(&self) -> CopyFormat {
+        self.format
+    }
+
+    /// Gets whether the first line holds the names of the columns.
+    pub fn get_header(&self) -> bool {
+        self.header
+    }
+
+    fn copy_sql(&self, query: &str) -> String {
+        let delimiter = match self.format {
+            CopyFormat::Csv => "','",
+            CopyFormat::Tsv => r"E'\t'",
+        };
+
+        format!(
+            "COPY (\n{}\n) TO STDOUT (FORMAT csv, HEADER {}, DELIMITER {})",
+            query, self.header, delimiter
+        )
+    }
+}
+
+impl PostgresExecutor {
+    /// Loads the records into the table with `COPY ... FROM STDIN` in the
+    /// binary format, returning the number of rows loaded. The records are
+    /// written as they are read from the iterator, and no row is loaded if
+    /// any of them fails.
+    pub fn copy_in<T, I>(&mut self, table: &str, rows: I) -> Result<u64, SqlExecutionError>
+    where
+        T: SqlRecord,
+        I: IntoIterator<Item = T>,
+    {
+        let columns = T::columns()
+            .iter()
+            .map(|column| quote_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // The binary format has to match the types of the columns exactly, so
+        // they are looked up before the copy starts.
+        let statement = self.prepare(&format!("SELECT {} FROM {}", columns, table))?;
+        let types = statement
+            .columns()
+            .iter()
+            .map(|column| column.type_().clone())
+            .collect::<Vec<_>>();
+
+        let interruptions = &self.interruptions;
+        let copy_sql = format!("COPY {} ({}) FROM STDIN (FORMAT binary)", table, columns);
+        let sink = self
+            .connection
+            .block_on(self.client.copy_in::<_, Bytes>(copy_sql.as_str()))
+            .map_err(|err| interruptions.execution_error(err))?;
+        let mut writer = Box::pin(BinaryCopyInWriter::new(sink, &types));
+
+        for row in rows {
+            let values = row
+                .into_values()
+                .into_iter()
+                .map(CopyValue)
+                .collect::<Vec<_>>();
+            if values.len() != types.len() {
+                let message = format!(
+                    "A record has {} values for the {} columns of {}",
+                    values.len(),
+                    types.len(),
+                    table
+                );
+                return Err(SqlExecutionError::Other {
+                    err: message.into(),
+                });
+            }
+
+            let value_refs = values
+                .iter()
+                .map(|value| value as &(dyn ToSql + Sync))
+                .collect::<Vec<_>>();
+            self.connection
+                .block_on(writer.as_mut().write(&value_refs))
+                .map_err(|err| interruptions.execution_error(err))?;
+        }
+
+        self.connection
+            .block_on(writer.as_mut().finish())
+            .map_err(|err| interruptions.execution_error(err))
+    }
+}
+
+impl PostgresExecutor {
+    /// Exports the rows returned by the query with `COPY (...) TO STDOUT`,
+    /// writing them to `writer` as they are received. Returns the number of
+    /// bytes written.
+    pub fn copy_out_to<W>(
+        &mut self,
+        sql: &str,
+        options: &CopyOutOptions,
+        mut writer: W,
+    ) -> Result<u64, SqlExecutionError>
+    where
+        W: Write,
+    {
+        let interruptions = &self.interruptions;
+        let copy_sql = options.copy_sql(copy_query(sql));
+        let stream = self
+            .connection
+            .block_on(self.client.copy_out(copy_sql.as_str()))
+            .map_err(|err| interruptions.execution_error(err))?;
+
+        let mut written = 0;
+        for bytes in self.connection.iter(stream) {
+            let bytes = bytes.map_err(|err| interruptions.execution_error(err))?;
+            writer
+                .write_all(&bytes)
+                .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })?;
+            written += bytes.len() as u64;
+        }
+
+        Ok(written)
+    }
+
+    /// Exports the rows returned by the query with `COPY (...) TO STDOUT` in
+    /// the binary format and decodes them as they are received.
+    pub fn copy_out_rows<'a>(&'a mut self, sql: &str) -> Result<ResultSet<'a>, SqlExecutionError> {
+        // The statement is only prepared to learn the types of the columns and
+        // is never executed, so a cached statement would not be invalidated
+        // once the types change.
+        let query = copy_query(sql);
+        let statement = self.prepare(query)?;
+        let description = describe_columns(statement.columns());
+        let columns: Arc<[String]> = statement
+            .columns()
+            .iter()
+            .map(|column| column.name().to_owned())
+            .collect();
+        let types = statement
+            .columns()
+            .iter()
+            .map(|column| column.type_().clone())
+            .collect::<Vec<_>>();
+
+        let interruptions = &self.interruptions;
+        let copy_sql = format!("COPY (\n{}\n) TO STDOUT (FORMAT binary)", query);
+        let stream = self
+            .connection
+            .block_on(self.client.copy_out(copy_sql.as_str()))
+            .map_err(|err| interruptions.execution_error(err))?;
+
+        let row_iter = self
+            .connection
+            .iter(BinaryCopyOutStream::new(stream, &types))
+            .map(move |copy_row| convert_copy_row(copy_row, &columns));
+        Ok(ResultSet::new(description, Box::new(row_iter)))
+    }
+}
+
+/// Removes the terminator of a script, which may not appear within `COPY`,
+/// along with any comments after it. The query is still placed on lines of
+/// its own, so that a script which ends with a line comment cannot comment
+/// out the rest of the `COPY`.
+fn copy_query(sql: &str) -> &str {
+    match split_statements(sql).as_slice() {
+        [query] => query,
+        _ => sql.trim(),
+    }
+}
+
+fn convert_copy_row(
+    copy_row: Result<BinaryCopyOutRow, tokio_postgres::Error>,
+    columns: &Arc<[String]>,
+) -> Result<SqlRow, RowConversionError> {
+    let copy_row = copy_row.map_err(|err| RawRowConversionError::Other { err: err.into() })?;
+
+    let values = (0..columns.len())
+        .map(|i| {
+            copy_row
+                .try_get::<Option<PgWormSqlValue>>(i)
+                .map(|value| value.map(|value| value.0).unwrap_or(SqlValue::Null))
+                .map_err(|err| RawRowConversionError::Other { err: err.into() })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(SqlRow::new(columns.clone(), values))
+}
+
+/// A value which is converted to the type of the column it is copied into,
+/// since unlike a parameter the type of a copied value is never inferred.
+#[derive(Debug)]
+struct CopyValue(SqlValue);
+
+impl ToSql for CopyValue {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        write_value(&self.0, ty, out)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        // The type is checked against the wrapped value in `to_sql`.
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// Writes a value as the type of a column or parameter. Whole numbers are
+/// written as any integer type they fit in, and a `Float32` is widened when
+/// a `FLOAT8` is expected.
+pub(crate) fn write_value(
+    value: &SqlValue,
+    ty: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    fn write<T>(
+        value: &T,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>>
+    where
+        T: ToSql,
+    {
+        if T::accepts(ty) {
+            value.to_sql(ty, out)
+        } else {
+            Err(Box::new(WrongType::new::<T>(ty.clone())))
+        }
+    }
+
+    match value {
+        SqlValue::Boolean(b) => write(b, ty, out),
+        SqlValue::Float32(f) if *ty == Type::FLOAT8 => write(&f64::from(*f), ty, out),
+        SqlValue::Float32(f) => write(f, ty, out),
+        SqlValue::Float64(f) => write(f, ty, out),
+        SqlValue::Null => Ok(IsNull::Yes),
+        SqlValue::String(string) => write(string, ty, out),
+        SqlValue::Signed8(i) => write_integer(i128::from(*i), ty, out),
+        SqlValue::Signed16(i) => write_integer(i128::from(*i), ty, out),
+        SqlValue::Signed32(i) => write_integer(i128::from(*i), ty, out),
+        SqlValue::Signed64(i) => write_integer(i128::from(*i), ty, out),
+        SqlValue::Signed128(i) => write_integer(*i, ty, out),
+        SqlValue::Unsigned8(u) => write_integer(i128::from(*u), ty, out),
+        SqlValue::Unsigned16(u) => write_integer(i128::from(*u), ty, out),
+        SqlValue::Unsigned32(u) => write_integer(i128::from(*u), ty, out),
+        SqlValue::Unsigned64(u) => write_integer(i128::from(*u), ty, out),
+        SqlValue::Unsigned128(u) => match i128::try_from(*u) {
+            Ok(i) => write_integer(i, ty, out),
+            Err(_) if *ty == Type::NUMERIC => {
+                write_numeric(false, *u, out);
+                Ok(IsNull::No)
+            }
+            Err(_) => Err(out_of_range(u, ty)),
+        },
+    }
+}
+
+/// Writes a whole number as any of the integer types, or as a numeric.
+fn write_integer(
+    value: i128,
+    ty: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    fn narrow<T>(
+        value: i128,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>>
+    where
+        T: ToSql + TryFrom<i128>,
+    {
+        match T::try_from(value) {
+            Ok(narrowed) => narrowed.to_sql(ty, out),
+            Err(_) => Err(out_of_range(value, ty)),
+        }
+    }
+
+    match *ty {
+        Type::CHAR => narrow::<i8>(value, ty, out),
+        Type::INT2 => narrow::<i16>(value, ty, out),
+        Type::INT4 => narrow::<i32>(value, ty, out),
+        Type::INT8 => narrow::<i64>(value, ty, out),
+        Type::OID => narrow::<u32>(value, ty, out),
+        Type::NUMERIC => {
+            write_numeric(value < 0, value.unsigned_abs(), out);
+            Ok(IsNull::No)
+        }
+        _ => Err(format!(
+            "The integer {} cannot be written as a value of type {}",
+            value, ty
+        )
+        .into()),
+    }
+}
+
+/// Writes a whole number in the binary format of a numeric, which stores the
+/// digits in base 10000 with the most significant digit first.
+fn write_numeric(negative: bool, mut magnitude: u128, out: &mut BytesMut) {
+    let mut digits = vec![];
+    while magnitude > 0 {
+        digits.push((magnitude % 10_000) as i16);
+        magnitude /= 10_000;
+    }
+    digits.reverse();
+
+    let sign: u16 = if negative { 0x4000 } else { 0x0000 };
+    let weight = digits.len().saturating_sub(1) as i16;
+
+    out.put_i16(digits.len() as i16);
+    out.put_i16(weight);
+    out.put_u16(sign);
+    // The display scale, which is the number of digits after the point.
+    out.put_u16(0);
+    for digit in digits {
+        out.put_i16(digit);
+    }
+}
+
+fn out_of_range(value: impl std::fmt::Display, ty: &Type) -> Box<dyn Error + Sync + Send> {
+    format!("The integer {} is out of range for the type {}", value, ty).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: SqlValue, ty: &Type) -> Result<Vec<u8>, String> {
+        let mut out = BytesMut::new();
+        match CopyValue(value).to_sql(ty, &mut out) {
+            Ok(IsNull::No) => Ok(out.to_vec()),
+            Ok(IsNull::Yes) => Ok(vec![]),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// The binary numeric of the header fields and base 10000 digits.
+    fn numeric(weight: i16, sign: u16, digits: &[i16]) -> Vec<u8> {
+        let mut out = BytesMut::new();
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight);
+        out.put_u16(sign);
+        out.put_u16(0);
+        for digit in digits {
+            out.put_i16(*digit);
+        }
+        out.to_vec()
+    }
+
+    #[test]
+    fn encodes_numerics_in_base_10000() {
+        assert_eq!(
+            encode(SqlValue::Signed32(0), &Type::NUMERIC),
+            Ok(numeric(0, 0, &[]))
+        );
+        assert_eq!(
+            encode(SqlValue::Signed32(9999), &Type::NUMERIC),
+            Ok(numeric(0, 0, &[9999]))
+        );
+        assert_eq!(
+            encode(SqlValue::Signed32(10_000), &Type::NUMERIC),
+            Ok(numeric(1, 0, &[1, 0]))
+        );
+        assert_eq!(
+            encode(SqlValue::Signed64(-123_456_789), &Type::NUMERIC),
+            Ok(numeric(2, 0x4000, &[1, 2345, 6789]))
+        );
+    }
+
+    #[test]
+    fn encodes_the_extremes_of_128_bit_integers_as_numerics() {
+        // 340282366920938463463374607431768211455
+        assert_eq!(
+            encode(SqlValue::Unsigned128(u128::MAX), &Type::NUMERIC),
+            Ok(numeric(
+                9,
+                0,
+                &[340, 2823, 6692, 938, 4634, 6337, 4607, 4317, 6821, 1455]
+            ))
+        );
+        // -170141183460469231731687303715884105728
+        assert_eq!(
+            encode(SqlValue::Signed128(i128::MIN), &Type::NUMERIC),
+            Ok(numeric(
+                9,
+                0x4000,
+                &[170, 1411, 8346, 469, 2317, 3168, 7303, 7158, 8410, 5728]
+            ))
+        );
+    }
+
+    #[test]
+    fn narrows_integers_to_the_column_type() {
+        assert_eq!(
+            encode(SqlValue::Signed64(300), &Type::INT2),
+            Ok(300i16.to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            encode(SqlValue::Unsigned8(7), &Type::INT8),
+            Ok(7i64.to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            encode(SqlValue::Unsigned64(u64::from(u32::MAX)), &Type::OID),
+            Ok(u32::MAX.to_be_bytes().to_vec())
+        );
+        assert_eq!(encode(SqlValue::Signed8(-1), &Type::CHAR), Ok(vec![0xff]));
+    }
+
+    #[test]
+    fn rejects_integers_which_do_not_fit() {
+        assert!(encode(SqlValue::Signed32(40_000), &Type::INT2).is_err());
+        assert!(encode(SqlValue::Signed64(-1), &Type::OID).is_err());
+        assert!(encode(SqlValue::Unsigned64(u64::MAX), &Type::INT8).is_err());
+        assert!(encode(SqlValue::Unsigned128(u128::MAX), &Type::INT8).is_err());
+        assert!(encode(SqlValue::Signed32(1), &Type::TEXT).is_err());
+    }
+
+    #[test]
+    fn widens_floats_to_float8() {
+        assert_eq!(
+            encode(SqlValue::Float32(1.1), &Type::FLOAT8),
+            Ok(f64::from(1.1f32).to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            encode(SqlValue::Float32(1.1), &Type::FLOAT4),
+            Ok(1.1f32.to_be_bytes().to_vec())
+        );
+        assert!(encode(SqlValue::Float64(1.1), &Type::FLOAT4).is_err());
+        assert!(encode(SqlValue::Float32(1.1), &Type::INT4).is_err());
+    }
+}
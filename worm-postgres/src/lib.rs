@@ -3,23 +3,66 @@
 #![warn(clippy::all)]
 #![warn(missing_docs)]
 
-use postgres::fallible_iterator::FallibleIterator;
-use postgres::types::FromSql;
-use postgres::types::Type;
-use postgres::Client;
-use postgres::Error as PostgresError;
-use postgres::NoTls;
-use postgres::Row;
+#[cfg(feature = "async")]
+mod async_executor;
+mod blocking;
+mod cancel;
+mod connection;
+mod copy;
+mod notify;
+mod options;
+mod statement_cache;
+mod tls;
+
+#[cfg(feature = "async")]
+pub use self::async_executor::AsyncPostgresExecutor;
+use self::blocking::BlockingConnection;
+use self::cancel::Interruptions;
+pub use self::connection::PostgresConnectionExt;
+pub use self::copy::CopyFormat;
+pub use self::copy::CopyOutOptions;
+pub use self::notify::Notification;
+pub use self::notify::Notifications;
+pub use self::options::ChannelBinding;
+pub use self::options::ConnectOptions;
+pub use self::options::Host;
+pub use self::options::LoadBalanceHosts;
+pub use self::options::SslMode;
+pub use self::options::TargetSessionAttrs;
+use self::statement_cache::StatementCache;
+pub use self::statement_cache::StatementCacheStats;
+pub use self::statement_cache::DEFAULT_STATEMENT_CACHE_CAPACITY;
+use self::tls::MakeTlsConnector;
+
+use bytes::BytesMut;
+use futures_util::future::join_all;
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::types::to_sql_checked;
+use tokio_postgres::types::FromSql;
+use tokio_postgres::types::IsNull;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::types::Type;
+use tokio_postgres::types::WrongType;
+use tokio_postgres::Client;
+use tokio_postgres::Row;
+use tokio_postgres::SimpleQueryMessage;
+use tokio_postgres::Statement;
 use worm::errors::ConnectionError;
 use worm::errors::RawRowConversionError;
 use worm::errors::RowConversionError;
 use worm::errors::SqlExecutionError;
-use worm::executors::ResultIter;
+use worm::executors::CancelHandle;
+use worm::executors::ResultSet;
 use worm::executors::SqlExecutor;
+use worm::sql::split_statements;
+use worm::sql::ColumnDescription;
 use worm::sql::RecordField;
 use worm::sql::SqlRow;
 use worm::sql::SqlValue;
+use worm::transactions::TransactionOptions;
+use worm::ScriptInfo;
 
 struct PgWormSqlValue(SqlValue);
 
@@ -32,6 +75,12 @@ impl<'a> FromSql<'a> for PgWormSqlValue {
         where
             T: RecordField + FromSql<'a>,
         {
+            // Many types share the length of their binary format, so a value
+            // has to be decoded as a type which matches the column.
+            if !<T as FromSql<'a>>::accepts(ty) {
+                return Err(Box::new(WrongType::new::<T>(ty.clone())));
+            }
+
             <T as FromSql<'a>>::from_sql(ty, raw).map(|t| PgWormSqlValue(t.into_sql()))
         }
 
@@ -49,59 +98,432 @@ impl<'a> FromSql<'a> for PgWormSqlValue {
         .iter()
         .map(|f| f(ty, raw));
 
-        Ok(results.filter_map(Result::ok).next().unwrap())
+        results
+            .filter_map(Result::ok)
+            .next()
+            .ok_or_else(|| Box::new(WrongType::new::<PgWormSqlValue>(ty.clone())).into())
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(PgWormSqlValue(SqlValue::Null))
     }
 
     fn accepts(ty: &Type) -> bool {
         [
-            bool::accepts,
-            f32::accepts,
-            f64::accepts,
-            String::accepts,
-            i8::accepts,
-            i16::accepts,
-            i32::accepts,
-            i64::accepts,
-            u32::accepts,
+            <bool as FromSql>::accepts,
+            <f32 as FromSql>::accepts,
+            <f64 as FromSql>::accepts,
+            <String as FromSql>::accepts,
+            <i8 as FromSql>::accepts,
+            <i16 as FromSql>::accepts,
+            <i32 as FromSql>::accepts,
+            <i64 as FromSql>::accepts,
+            <u32 as FromSql>::accepts,
         ]
         .iter()
         .any(|f| f(ty))
     }
 }
 
-/// An worm executor for postgres
-pub struct PostgresExecutor(Client);
+/// A parameter which is converted to the type the server inferred for it,
+/// which callers of SQL only known at runtime cannot predict. Integers are
+/// narrowed or widened like the values of a copy.
+#[derive(Debug)]
+struct PgWormSqlParam<'a>(&'a SqlValue);
+
+impl<'a> ToSql for PgWormSqlParam<'a> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        copy::write_value(self.0, ty, out)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        // The type is checked against the wrapped value in `to_sql`.
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// An worm executor for postgres.
+///
+/// Statements are prepared once and kept in a per-connection cache, so that
+/// executing the same SQL again skips parsing and planning. The statement of
+/// a script is cached under the script, so a script which inlines its values
+/// takes a single entry which is replaced whenever its SQL changes. Other SQL
+/// is cached under its text. Statements which worm executes internally, and
+/// the statements of a script which returns several result sets, are not
+/// cached.
+///
+/// Timeouts are applied with `statement_timeout`, which is only changed when
+/// a statement needs a different timeout than the one before it.
+///
+/// The executor drives an asynchronous client on a runtime of its own, so
+/// the statements of a batch are pipelined: they are all sent before the
+/// responses are read, and the batch only waits for the server once.
+pub struct PostgresExecutor {
+    // The client is dropped before its connection, which then closes.
+    client: Client,
+    connection: BlockingConnection,
+    statements: StatementCache,
+    script: Option<&'static str>,
+    interruptions: Interruptions,
+    tls: Option<MakeTlsConnector>,
+}
+
+impl PostgresExecutor {
+    /// Connects to the database described by the options.
+    pub fn connect_with(options: &ConnectOptions) -> Result<Self, ConnectionError> {
+        let (client, connection, tls) = tls::connect(options)
+            .map_err(|err| ConnectionError::with_source(options.to_string(), err))?;
+
+        Ok(Self {
+            client,
+            connection,
+            statements: StatementCache::new(DEFAULT_STATEMENT_CACHE_CAPACITY),
+            script: None,
+            interruptions: Interruptions::new(),
+            tls,
+        })
+    }
+
+    /// Sets the number of prepared statements which are cached. The least
+    /// recently used statements are closed if the cache shrinks. A capacity
+    /// of zero disables the cache.
+    pub fn set_statement_cache_capacity(&mut self, capacity: usize) {
+        self.statements.resize(capacity);
+    }
+
+    /// The number of prepared statements which are cached.
+    pub fn statement_cache_capacity(&self) -> usize {
+        self.statements.capacity()
+    }
+
+    /// The number of times a statement was or was not found in the cache.
+    pub fn statement_cache_stats(&self) -> StatementCacheStats {
+        self.statements.stats()
+    }
+
+    /// Finds the prepared statement for the SQL, preparing it if it is not
+    /// cached.
+    fn prepare_cached(
+        &mut self,
+        script: Option<&'static str>,
+        sql: &str,
+    ) -> Result<Statement, SqlExecutionError> {
+        if let Some(statement) = self.statements.get(script, sql) {
+            return Ok(statement);
+        }
+
+        let statement = self.prepare(sql)?;
+        self.statements.insert(script, sql, statement.clone());
+        Ok(statement)
+    }
+
+    /// Prepares a statement without caching it, for the statements which are
+    /// executed internally rather than by the user.
+    fn prepare(&mut self, sql: &str) -> Result<Statement, SqlExecutionError> {
+        self.connection
+            .block_on(self.client.prepare(sql))
+            .map_err(|err| self.interruptions.execution_error(err))
+    }
+
+    /// Executes statements which take no parameters and return no rows using
+    /// the simple query protocol.
+    fn batch_execute(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        self.connection
+            .block_on(self.client.batch_execute(sql))
+            .map_err(|err| self.interruptions.execution_error(err))
+    }
+
+    /// Runs a statement which takes no parameters and reads all of its rows.
+    fn query_all(&mut self, statement: &Statement) -> Result<Vec<Row>, SqlExecutionError> {
+        self.connection
+            .block_on(self.client.query(statement, &[]))
+            .map_err(|err| self.interruptions.execution_error(err))
+    }
+}
 
 impl SqlExecutor for PostgresExecutor {
     fn connect(connection_string: &str) -> Result<Self, ConnectionError>
     where
         Self: Sized,
     {
-        match Client::connect(connection_string, NoTls) {
-            Ok(client) => Ok(Self(client)),
-            Err(_) => Err(ConnectionError::new(connection_string.to_owned())),
-        }
+        Self::connect_with(&ConnectOptions::parse(connection_string)?)
     }
 
-    fn execute_sql<'a>(&'a mut self, sql: &str) -> Result<ResultIter<'a>, SqlExecutionError> {
-        let client = &mut self.0;
-        let pg_row_iter = match client.query_raw::<_, bool, _>(sql, core::iter::empty()) {
-            Ok(row_iter) => row_iter,
-            Err(err) => return Err(SqlExecutionError::Other { err: Box::new(err) }),
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        let script = self.script.take();
+        let statement = self.prepare_cached(script, sql)?;
+        let description = describe_columns(statement.columns());
+        let pg_params = params.iter().map(PgWormSqlParam);
+        let pg_row_stream = match self
+            .connection
+            .block_on(self.client.query_raw(&statement, pg_params))
+        {
+            Ok(row_stream) => row_stream,
+            Err(err) => {
+                self.statements.invalidate(script, sql, &err);
+                return Err(self.interruptions.execution_error(err));
+            }
         };
+        let mut pg_row_iter = self.connection.iter(pg_row_stream);
+
+        // The server reports most failures, like a timeout, before the first
+        // row, so reading it here lets them surface as execution errors.
+        let first_row = match pg_row_iter.next() {
+            Some(Err(err)) => return Err(self.interruptions.execution_error(err)),
+            first_row => first_row,
+        };
+
+        let interruptions = &self.interruptions;
+        let mut columns = None;
+        let worm_row_iter = first_row.into_iter().chain(pg_row_iter).map(move |pg_row| {
+            let pg_row = pg_row.map_err(|err| interruptions.execution_error(err));
+            convert_row(pg_row, &mut columns)
+        });
+        Ok(ResultSet::new(description, Box::new(worm_row_iter)))
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let pg_params = params.iter().map(PgWormSqlParam).collect::<Vec<_>>();
+        let pg_param_refs = pg_params
+            .iter()
+            .map(|param| param as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+
+        let script = self.script.take();
+        let statement = self.prepare_cached(script, sql)?;
+        self.connection
+            .block_on(self.client.execute(&statement, &pg_param_refs))
+            .map_err(|err| {
+                self.statements.invalidate(script, sql, &err);
+                self.interruptions.execution_error(err)
+            })
+    }
+
+    fn execute_sql_batch(&mut self, statements: &[String]) -> Vec<Result<u64, SqlExecutionError>> {
+        // Waiting on the statements together pipelines them over the
+        // connection. Each statement is synchronized on its own, so one which
+        // fails does not stop the others.
+        self.script = None;
+        let client = &self.client;
+        let executions = statements
+            .iter()
+            .map(|sql| client.execute(sql.as_str(), &[]));
+        let interruptions = &self.interruptions;
+        match self
+            .connection
+            .block_on(async { Ok(join_all(executions).await) })
+        {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.map_err(|err| interruptions.execution_error(err)))
+                .collect(),
+            Err(err) => {
+                let err = interruptions.execution_error(err).to_string();
+                statements
+                    .iter()
+                    .map(|_| {
+                        Err(SqlExecutionError::Other {
+                            err: err.clone().into(),
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        // The statements are sent in a single round trip with the simple query
+        // protocol, which stops at the first statement that fails. Each
+        // separator starts on a new line so that a statement which ends with a
+        // line comment cannot comment it out.
+        let sql = statements
+            .iter()
+            .map(|statement| format!("{}\n;", statement))
+            .collect::<String>();
+        self.script = None;
+        let messages = self
+            .connection
+            .block_on(self.client.simple_query(&sql))
+            .map_err(|err| self.interruptions.execution_error(err))?;
+
+        let affected = messages
+            .into_iter()
+            .filter_map(|message| match message {
+                SimpleQueryMessage::CommandComplete(affected) => Some(affected),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if affected.len() == statements.len() {
+            Ok(affected)
+        } else {
+            let message = format!(
+                "The batch of {} scripts executed {} statements. Each script in a batch must be a single statement.",
+                statements.len(),
+                affected.len()
+            );
+            Err(SqlExecutionError::Other {
+                err: message.into(),
+            })
+        }
+    }
+
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        // The statements only run as part of the script, so caching them
+        // would push out the statements which are executed on their own.
+        self.script = None;
+        let mut sets = Vec::new();
+        for sql in split_statements(sql) {
+            let statement = self.prepare(sql)?;
+            let pg_rows = self.query_all(&statement)?;
+
+            if !statement.columns().is_empty() || !pg_rows.is_empty() {
+                let mut columns = None;
+                let rows = pg_rows
+                    .into_iter()
+                    .map(|pg_row| convert_row::<tokio_postgres::Error>(Ok(pg_row), &mut columns))
+                    .collect::<Vec<_>>();
+                let description = describe_columns(statement.columns());
+                sets.push(ResultSet::new(description, Box::new(rows.into_iter())));
+            }
+        }
 
-        let worm_row_iter = pg_row_iter.iterator().map(convert_row);
-        let worm_row_iter_box = Box::new(worm_row_iter);
-        Ok(worm_row_iter_box)
+        Ok(sets)
+    }
+
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        self.batch_execute(sql)
+    }
+
+    fn on_script(&mut self, script: &ScriptInfo) {
+        self.script = Some(script.name());
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SqlExecutionError> {
+        if let Some(sql) = self.interruptions.timeout_sql(timeout) {
+            self.batch_execute(&sql)?;
+        }
+
+        self.interruptions.timeout_set(timeout);
+        Ok(())
+    }
+
+    fn cancel_handle(&self) -> Option<CancelHandle> {
+        let canceller = self.interruptions.canceller(
+            self.client.cancel_token(),
+            self.tls.as_ref().map(tls::clone_connector),
+        );
+        Some(CancelHandle::new(canceller))
+    }
+
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        self.batch_execute(&options.begin_sql())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.batch_execute("COMMIT")
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        // A timeout set within the transaction is undone with it.
+        self.interruptions.rolled_back();
+        self.batch_execute("ROLLBACK")
+    }
+
+    fn create_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.batch_execute(&format!("SAVEPOINT {}", name))
+    }
+
+    fn release_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.batch_execute(&format!("RELEASE SAVEPOINT {}", name))
+    }
+
+    fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.interruptions.rolled_back();
+        self.batch_execute(&format!("ROLLBACK TO SAVEPOINT {}", name))
     }
 }
 
-fn convert_row(pg_row: Result<Row, PostgresError>) -> Result<SqlRow, RowConversionError> {
+fn convert_row<E>(
+    pg_row: Result<Row, E>,
+    columns: &mut Option<Arc<[String]>>,
+) -> Result<SqlRow, RowConversionError>
+where
+    E: Into<Box<dyn Error>>,
+{
     let pg_row = pg_row.map_err(|err| RawRowConversionError::Other { err: err.into() })?;
 
-    let row = (0..pg_row.len())
-        .map(|i| pg_row.get::<_, PgWormSqlValue>(i).0)
-        .collect();
+    // Every row of a result set shares the column names of the first row.
+    let columns = columns
+        .get_or_insert_with(|| {
+            pg_row
+                .columns()
+                .iter()
+                .map(|column| column.name().to_owned())
+                .collect()
+        })
+        .clone();
 
-    Ok(row)
+    let values = (0..pg_row.len())
+        .map(|i| pg_row.try_get::<_, PgWormSqlValue>(i).map(|value| value.0))
+        .collect::<Result<_, _>>()
+        .map_err(|err| RawRowConversionError::Other { err: err.into() })?;
+
+    Ok(SqlRow::new(columns, values))
+}
+
+/// Describes the columns of a statement. The server does not report whether
+/// the columns of a result may be null, and only reports the OID of the table
+/// a column was read from rather than its name. Looking the names up would
+/// cost a query whenever a statement is prepared.
+fn describe_columns(columns: &[tokio_postgres::Column]) -> Arc<[ColumnDescription]> {
+    columns
+        .iter()
+        .map(|column| {
+            let description = ColumnDescription::new(column.name())
+                .type_name(column.type_().name())
+                .type_id(column.type_().oid());
+            match column.table_oid() {
+                Some(table_oid) => description.table_id(table_oid),
+                None => description,
+            }
+        })
+        .collect()
+}
+
+/// Quotes an identifier, like the name of a column or a channel, so that it
+/// keeps its case and may contain any character.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_values_of_unsupported_types() {
+        let decoded = PgWormSqlValue::from_sql(&Type::BYTEA, b"raw");
+        assert!(decoded.is_err());
+    }
 }
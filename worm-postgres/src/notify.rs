@@ -0,0 +1,173 @@
+use crate::blocking::BlockingConnection;
+use crate::quote_identifier;
+use crate::PostgresExecutor;
+use std::future::Future;
+use std::task::Poll;
+use std::time::Duration;
+use worm::errors::SqlExecutionError;
+
+/// A notification sent with `NOTIFY` to a channel the connection listens on.
+/// The payload is a string unless the notifications are decoded as JSON.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Notification<T = String> {
+    channel: String,
+    payload: T,
+    process_id: i32,
+}
+
+impl<T> Notification<T> {
+    /// The channel the notification was sent to.
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// The payload the notification was sent with.
+    pub fn payload(&self) -> &T {
+        &self.payload
+    }
+
+    /// Consumes the notification, returning its payload.
+    pub fn into_payload(self) -> T {
+        self.payload
+    }
+
+    /// The process ID of the server backend which sent the notification.
+    pub fn process_id(&self) -> i32 {
+        self.process_id
+    }
+}
+
+/// An iterator over the notifications received by a connection, which blocks
+/// until the next notification arrives. If a timeout is set, the iterator
+/// ends once no notification arrives within it.
+pub struct Notifications<'a, T = String> {
+    connection: &'a mut BlockingConnection,
+    timeout: Option<Duration>,
+    decode: fn(String) -> Result<T, SqlExecutionError>,
+}
+
+impl<'a> Notifications<'a> {
+    /// Decodes the payload of each notification as JSON.
+    #[cfg(feature = "serde")]
+    pub fn json<T>(self) -> Notifications<'a, T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Notifications {
+            connection: self.connection,
+            timeout: self.timeout,
+            decode: |payload| {
+                serde_json::from_str(&payload)
+                    .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })
+            },
+        }
+    }
+}
+
+impl<'a, T> Notifications<'a, T> {
+    /// Sets how long the iterator waits for the next notification before it
+    /// ends. A timeout of zero only yields the notifications which have
+    /// already been received.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl<'a, T> Iterator for Notifications<'a, T> {
+    type Item = Result<Notification<T>, SqlExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let connection = &mut *self.connection;
+        let next = match connection.notifications_mut().pop_front() {
+            Some(notification) => Ok(Some(notification)),
+            None => match self.timeout {
+                Some(timeout) if timeout == Duration::from_secs(0) => {
+                    connection.poll_block_on(|_, notifications, _| {
+                        Poll::Ready(Ok(notifications.pop_front()))
+                    })
+                }
+                Some(timeout) => {
+                    let mut delay = {
+                        let _runtime = connection.runtime().enter();
+                        Box::pin(tokio::time::sleep(timeout))
+                    };
+                    connection.poll_block_on(|cx, notifications, closed| {
+                        match notifications.pop_front() {
+                            Some(notification) => Poll::Ready(Ok(Some(notification))),
+                            None if closed => Poll::Ready(Ok(None)),
+                            None => delay.as_mut().poll(cx).map(|()| Ok(None)),
+                        }
+                    })
+                }
+                None => connection.poll_block_on(|_, notifications, closed| {
+                    match notifications.pop_front() {
+                        Some(notification) => Poll::Ready(Ok(Some(notification))),
+                        None if closed => Poll::Ready(Ok(None)),
+                        None => Poll::Pending,
+                    }
+                }),
+            },
+        };
+
+        let notification = match next {
+            Ok(notification) => notification?,
+            Err(err) => return Some(Err(SqlExecutionError::Other { err: Box::new(err) })),
+        };
+
+        let payload = match (self.decode)(notification.payload().to_owned()) {
+            Ok(payload) => payload,
+            Err(err) => return Some(Err(err)),
+        };
+
+        Some(Ok(Notification {
+            channel: notification.channel().to_owned(),
+            payload,
+            process_id: notification.process_id(),
+        }))
+    }
+}
+
+impl PostgresExecutor {
+    /// Starts listening for notifications sent to the channel.
+    pub fn listen(&mut self, channel: &str) -> Result<(), SqlExecutionError> {
+        self.batch_execute(&format!("LISTEN {}", quote_identifier(channel)))
+    }
+
+    /// Stops listening for notifications sent to the channel.
+    pub fn unlisten(&mut self, channel: &str) -> Result<(), SqlExecutionError> {
+        self.batch_execute(&format!("UNLISTEN {}", quote_identifier(channel)))
+    }
+
+    /// Sends a notification to every connection listening on the channel.
+    /// Within a transaction, the notification is only sent once it commits.
+    pub fn notify(&mut self, channel: &str, payload: &str) -> Result<(), SqlExecutionError> {
+        let statement = self.prepare("SELECT pg_notify($1, $2)")?;
+        self.connection
+            .block_on(self.client.execute(&statement, &[&channel, &payload]))
+            .map(|_| ())
+            .map_err(|err| self.interruptions.execution_error(err))
+    }
+
+    /// Sends a notification with the payload encoded as JSON.
+    #[cfg(feature = "serde")]
+    pub fn notify_json<T>(&mut self, channel: &str, payload: &T) -> Result<(), SqlExecutionError>
+    where
+        T: serde::Serialize,
+    {
+        let payload = serde_json::to_string(payload)
+            .map_err(|err| SqlExecutionError::Other { err: Box::new(err) })?;
+        self.notify(channel, &payload)
+    }
+
+    /// The notifications received on the channels the connection listens
+    /// on. Notifications are only received while the connection is used, so
+    /// the iterator waits on the connection for them.
+    pub fn notifications(&mut self) -> Notifications<'_> {
+        Notifications {
+            connection: &mut self.connection,
+            timeout: None,
+            decode: Ok,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use lru::LruCache;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Error as PostgresError;
+use tokio_postgres::Statement;
+
+/// The number of statements a [PostgresExecutor](crate::PostgresExecutor)
+/// caches unless configured otherwise.
+pub const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 100;
+
+/// Counters which describe how often a statement cache was able to skip
+/// preparing a statement.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StatementCacheStats {
+    hits: u64,
+    misses: u64,
+}
+
+impl StatementCacheStats {
+    /// The number of statements which were found in the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The number of statements which had to be prepared.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+/// What a statement is cached under. A script may inline its values, which
+/// compiles it to different SQL each time, so the statements of a script are
+/// cached under the script instead of under their SQL. That way a script
+/// only ever takes a single entry rather than filling the cache with
+/// statements which are never executed again.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum CacheKey {
+    Script(&'static str),
+    Sql(String),
+}
+
+impl CacheKey {
+    fn new(script: Option<&'static str>, sql: &str) -> Self {
+        match script {
+            Some(script) => CacheKey::Script(script),
+            None => CacheKey::Sql(sql.to_owned()),
+        }
+    }
+}
+
+/// A prepared statement along with the SQL it was prepared from.
+struct CachedStatement {
+    sql: String,
+    statement: Statement,
+}
+
+/// The prepared statements of a connection, keyed by the script they were
+/// compiled from or else by their SQL. The least recently used statement is
+/// closed once the cache is full.
+pub(crate) struct StatementCache {
+    statements: LruCache<CacheKey, CachedStatement>,
+    stats: StatementCacheStats,
+}
+
+impl StatementCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            statements: LruCache::new(capacity),
+            stats: StatementCacheStats::default(),
+        }
+    }
+
+    /// Finds the statement prepared for the SQL. The statement cached for a
+    /// script is only used if the script compiled to the same SQL.
+    pub fn get(&mut self, script: Option<&'static str>, sql: &str) -> Option<Statement> {
+        let statement = match self.statements.get(&CacheKey::new(script, sql)) {
+            Some(cached) if cached.sql == sql => Some(cached.statement.clone()),
+            _ => None,
+        };
+
+        match statement {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+
+        statement
+    }
+
+    /// Caches the statement, replacing the statement cached for the same
+    /// script.
+    pub fn insert(&mut self, script: Option<&'static str>, sql: &str, statement: Statement) {
+        let cached = CachedStatement {
+            sql: sql.to_owned(),
+            statement,
+        };
+
+        self.statements.put(CacheKey::new(script, sql), cached);
+    }
+
+    /// Removes the statement prepared for the SQL if the error shows that the
+    /// server can no longer use it, so that it is prepared again the next
+    /// time it is executed.
+    pub fn invalidate(&mut self, script: Option<&'static str>, sql: &str, err: &PostgresError) {
+        if is_stale_statement(err) {
+            self.statements.pop(&CacheKey::new(script, sql));
+        }
+    }
+
+    pub fn resize(&mut self, capacity: usize) {
+        self.statements.resize(capacity);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.statements.cap()
+    }
+
+    pub fn stats(&self) -> StatementCacheStats {
+        self.stats
+    }
+}
+
+/// Checks if the error reports that a prepared statement must be prepared
+/// again, which happens when the tables it uses are altered or when the
+/// server discards it.
+fn is_stale_statement(err: &PostgresError) -> bool {
+    match err.code() {
+        Some(code) if *code == SqlState::INVALID_SQL_STATEMENT_NAME => true,
+        Some(code) if *code == SqlState::FEATURE_NOT_SUPPORTED => err
+            .as_db_error()
+            .map(|db_err| db_err.message().starts_with("cached plan must not change"))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
@@ -0,0 +1,358 @@
+use crate::blocking::BlockingConnection;
+use crate::ConnectOptions;
+use crate::SslMode;
+use std::error::Error;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use std::path::Path;
+use tokio::runtime::Builder;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// The connector which encrypts connections. If both TLS features are
+/// enabled, native-tls is used.
+#[cfg(feature = "native-tls")]
+pub(crate) type MakeTlsConnector = postgres_native_tls::MakeTlsConnector;
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+pub(crate) type MakeTlsConnector = tokio_postgres_rustls::MakeRustlsConnect;
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+pub(crate) type MakeTlsConnector = NoTls;
+
+/// Connects a client whose connection is driven on a runtime of its own,
+/// encrypting the connection as the TLS options require. The connector is
+/// returned as well, since requests to cancel a statement are sent over
+/// connections of their own which must be encrypted alike.
+pub(crate) fn connect(
+    options: &ConnectOptions,
+) -> Result<(Client, BlockingConnection, Option<MakeTlsConnector>), BoxError> {
+    let config = options.config()?;
+    let connector = connector(options)?;
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let (client, connection) = match &connector {
+        Some(connector) => {
+            let (client, connection) =
+                runtime.block_on(config.connect(clone_connector(connector)))?;
+            (client, BlockingConnection::new(runtime, connection))
+        }
+        None => {
+            let (client, connection) = runtime.block_on(config.connect(NoTls))?;
+            (client, BlockingConnection::new(runtime, connection))
+        }
+    };
+
+    Ok((client, connection, connector))
+}
+
+/// Copies the connector for another connection. Without the TLS features the
+/// connector is `NoTls`, which is `Copy`.
+#[allow(clippy::clone_on_copy)]
+pub(crate) fn clone_connector(connector: &MakeTlsConnector) -> MakeTlsConnector {
+    connector.clone()
+}
+
+/// Connects an asynchronous client, encrypting the connection as the TLS
+/// options require. The connection is driven by a task spawned onto the
+/// current tokio runtime.
+#[cfg(feature = "async")]
+pub(crate) async fn connect_async(
+    options: &ConnectOptions,
+) -> Result<tokio_postgres::Client, BoxError> {
+    let config = options.config()?;
+    let client = match connector(options)? {
+        Some(connector) => {
+            let (client, connection) = config.connect(connector).await?;
+            spawn_connection(connection);
+            client
+        }
+        None => {
+            let (client, connection) = config.connect(NoTls).await?;
+            spawn_connection(connection);
+            client
+        }
+    };
+
+    Ok(client)
+}
+
+#[cfg(feature = "async")]
+fn spawn_connection<F>(connection: F)
+where
+    F: std::future::Future<Output = Result<(), tokio_postgres::Error>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        // Errors surface through the client once the connection has closed.
+        let _ = connection.await;
+    });
+}
+
+/// How much of the certificate of the server is checked.
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Verification {
+    None,
+    Chain,
+    Full,
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn verification(options: &ConnectOptions) -> Verification {
+    match options.get_ssl_mode() {
+        SslMode::VerifyFull => Verification::Full,
+        SslMode::VerifyCa => Verification::Chain,
+        // Like libpq, a root certificate makes require act as verify-ca.
+        SslMode::Require if options.get_ssl_root_cert().is_some() => Verification::Chain,
+        _ => Verification::None,
+    }
+}
+
+/// The certificate and key which identify the client, in PEM.
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+struct ClientIdentity {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+/// Reads the certificate and key which identify the client.
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn client_identity(options: &ConnectOptions) -> Result<Option<ClientIdentity>, BoxError> {
+    match (options.get_ssl_cert(), options.get_ssl_key()) {
+        (Some(cert), Some(key)) => Ok(Some(ClientIdentity {
+            cert: read_file(cert)?,
+            key: read_file(key)?,
+        })),
+        (None, None) => Ok(None),
+        _ => Err("sslcert and sslkey must be set together".into()),
+    }
+}
+
+/// Creates the connector which encrypts the connection, or `None` if the
+/// connection is not encrypted.
+fn connector(options: &ConnectOptions) -> Result<Option<MakeTlsConnector>, BoxError> {
+    if options.get_ssl_mode() == SslMode::Disable {
+        return Ok(None);
+    }
+
+    build_connector(options)
+}
+
+#[cfg(feature = "native-tls")]
+fn build_connector(options: &ConnectOptions) -> Result<Option<MakeTlsConnector>, BoxError> {
+    use native_tls::Certificate;
+    use native_tls::Identity;
+    use native_tls::TlsConnector;
+
+    let mut builder = TlsConnector::builder();
+    if let Some(root_cert) = options.get_ssl_root_cert() {
+        builder.add_root_certificate(Certificate::from_pem(&read_file(root_cert)?)?);
+    }
+
+    if let Some(identity) = client_identity(options)? {
+        builder.identity(Identity::from_pkcs8(&identity.cert, &identity.key)?);
+    }
+
+    match verification(options) {
+        Verification::None => {
+            builder.danger_accept_invalid_certs(true);
+            builder.danger_accept_invalid_hostnames(true);
+        }
+        Verification::Chain => {
+            builder.danger_accept_invalid_hostnames(true);
+        }
+        Verification::Full => {}
+    }
+
+    Ok(Some(MakeTlsConnector::new(builder.build()?)))
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+fn build_connector(options: &ConnectOptions) -> Result<Option<MakeTlsConnector>, BoxError> {
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::CertificateDer;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::ClientConfig;
+    use rustls::RootCertStore;
+    use std::sync::Arc;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier: Arc<dyn ServerCertVerifier> = match verification(options) {
+        Verification::None => Arc::new(rustls_verifiers::AcceptAnyCertificate(provider.clone())),
+        verification => {
+            // Unlike native-tls, rustls does not use the certificates of the
+            // system, so the authority has to be named.
+            let root_cert = options.get_ssl_root_cert().ok_or(
+                "sslrootcert must be set to verify the certificate of the server with rustls",
+            )?;
+
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_slice_iter(&read_file(root_cert)?) {
+                roots.add(cert?)?;
+            }
+
+            let verifier =
+                WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .build()?;
+            match verification {
+                Verification::Chain => Arc::new(rustls_verifiers::IgnoreHostname(verifier)),
+                _ => verifier,
+            }
+        }
+    };
+
+    let builder = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier);
+
+    let config = match client_identity(options)? {
+        Some(identity) => {
+            let certs =
+                CertificateDer::pem_slice_iter(&identity.cert).collect::<Result<Vec<_>, _>>()?;
+            let key = PrivateKeyDer::from_pem_slice(&identity.key)?;
+            builder.with_client_auth_cert(certs, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    Ok(Some(MakeTlsConnector::new(config)))
+}
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+fn build_connector(options: &ConnectOptions) -> Result<Option<MakeTlsConnector>, BoxError> {
+    match options.get_ssl_mode() {
+        SslMode::Prefer => Ok(None),
+        _ => Err(
+            "Encrypted connections need the native-tls or rustls feature of worm-postgres".into(),
+        ),
+    }
+}
+
+/// The verifiers used by rustls for the modes which do not check the whole
+/// certificate of the server.
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+mod rustls_verifiers {
+    use rustls::client::danger::HandshakeSignatureValid;
+    use rustls::client::danger::ServerCertVerified;
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::crypto::CryptoProvider;
+    use rustls::pki_types::CertificateDer;
+    use rustls::pki_types::ServerName;
+    use rustls::pki_types::UnixTime;
+    use rustls::CertificateError;
+    use rustls::DigitallySignedStruct;
+    use rustls::Error;
+    use rustls::SignatureScheme;
+    use std::sync::Arc;
+
+    /// Accepts any certificate, while still checking that the server holds
+    /// its key.
+    #[derive(Debug)]
+    pub struct AcceptAnyCertificate(pub Arc<CryptoProvider>);
+
+    impl ServerCertVerifier for AcceptAnyCertificate {
+        fn verify_server_cert(
+            &self,
+            _: &CertificateDer<'_>,
+            _: &[CertificateDer<'_>],
+            _: &ServerName<'_>,
+            _: &[u8],
+            _: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            rustls::crypto::verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            rustls::crypto::verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    /// Verifies that the certificate is signed by a trusted authority, but
+    /// not that it was issued for the host.
+    #[derive(Debug)]
+    pub struct IgnoreHostname(pub Arc<WebPkiServerVerifier>);
+
+    impl ServerCertVerifier for IgnoreHostname {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            intermediates: &[CertificateDer<'_>],
+            server_name: &ServerName<'_>,
+            ocsp_response: &[u8],
+            now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            let verified = self.0.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            );
+
+            match verified {
+                Err(Error::InvalidCertificate(CertificateError::NotValidForName))
+                | Err(Error::InvalidCertificate(CertificateError::NotValidForNameContext {
+                    ..
+                })) => Ok(ServerCertVerified::assertion()),
+                verified => verified,
+            }
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            self.0.verify_tls12_signature(message, cert, dss)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            self.0.verify_tls13_signature(message, cert, dss)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.supported_verify_schemes()
+        }
+    }
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn read_file(path: &Path) -> Result<Vec<u8>, BoxError> {
+    std::fs::read(path).map_err(|err| format!("Unable to read {}: {}", path.display(), err).into())
+}
@@ -0,0 +1,253 @@
+//! A crate that provides a Worm SQL executor for SQLite
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+use core::convert::TryFrom;
+use rusqlite::types::ToSqlOutput;
+use rusqlite::types::Value;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use rusqlite::Error as SqliteError;
+use rusqlite::Row;
+use rusqlite::ToSql;
+use std::sync::Arc;
+use worm::errors::ConnectionError;
+use worm::errors::RawRowConversionError;
+use worm::errors::RowConversionError;
+use worm::errors::SqlExecutionError;
+use worm::executors::ResultSet;
+use worm::executors::SqlExecutor;
+use worm::sql::ColumnDescription;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+use worm::transactions::IsolationLevel;
+use worm::transactions::TransactionOptions;
+
+struct SqliteWormSqlParam<'a>(&'a SqlValue);
+
+impl<'a> ToSql for SqliteWormSqlParam<'a> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        fn integer<T>(value: T) -> rusqlite::Result<ToSqlOutput<'static>>
+        where
+            i64: TryFrom<T>,
+            <i64 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+        {
+            i64::try_from(value)
+                .map(|i| ToSqlOutput::Owned(Value::Integer(i)))
+                .map_err(|err| SqliteError::ToSqlConversionFailure(Box::new(err)))
+        }
+
+        match self.0 {
+            SqlValue::Boolean(b) => integer(*b as i64),
+            SqlValue::Float32(f) => Ok(ToSqlOutput::Owned(Value::Real(*f as f64))),
+            SqlValue::Float64(f) => Ok(ToSqlOutput::Owned(Value::Real(*f))),
+            SqlValue::Null => Ok(ToSqlOutput::Owned(Value::Null)),
+            SqlValue::String(string) => {
+                Ok(ToSqlOutput::Borrowed(ValueRef::Text(string.as_bytes())))
+            }
+            SqlValue::Signed8(i) => integer(*i),
+            SqlValue::Signed16(i) => integer(*i),
+            SqlValue::Signed32(i) => integer(*i),
+            SqlValue::Signed64(i) => integer(*i),
+            SqlValue::Signed128(i) => integer(*i),
+            SqlValue::Unsigned8(u) => integer(*u),
+            SqlValue::Unsigned16(u) => integer(*u),
+            SqlValue::Unsigned32(u) => integer(*u),
+            SqlValue::Unsigned64(u) => integer(*u),
+            SqlValue::Unsigned128(u) => integer(*u),
+        }
+    }
+}
+
+/// A worm executor for SQLite.
+///
+/// The connection string is either `:memory:` (optionally prefixed by
+/// `sqlite:`) for a private in-memory database, or the path of a database
+/// file (optionally prefixed by `sqlite://`).
+pub struct SqliteExecutor(Connection);
+
+impl SqlExecutor for SqliteExecutor {
+    fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let path = connection_string
+            .strip_prefix("sqlite://")
+            .or_else(|| connection_string.strip_prefix("sqlite:"))
+            .unwrap_or(connection_string);
+
+        let connection = if path == ":memory:" {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(path)
+        };
+
+        match connection {
+            Ok(connection) => Ok(Self(connection)),
+            Err(_) => Err(ConnectionError::new(connection_string.to_owned())),
+        }
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        let sql = render_placeholders(sql);
+        let mut statement = self.0.prepare(&sql).map_err(execution_error)?;
+        let columns: Arc<[String]> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        // SQLite only knows the declared types of columns read from tables.
+        let description: Arc<[ColumnDescription]> = statement
+            .columns()
+            .iter()
+            .map(|column| match column.decl_type() {
+                Some(decl_type) => ColumnDescription::new(column.name()).type_name(decl_type),
+                None => ColumnDescription::new(column.name()),
+            })
+            .collect();
+
+        // The rows borrow the statement, so they are read before returning.
+        let sqlite_params = params.iter().map(SqliteWormSqlParam);
+        let mut sqlite_rows = statement.query(sqlite_params).map_err(execution_error)?;
+        let mut rows = vec![];
+        loop {
+            match sqlite_rows.next() {
+                Ok(Some(sqlite_row)) => rows.push(convert_row(sqlite_row, &columns)),
+                Ok(None) => break,
+                Err(err) => {
+                    rows.push(Err(RawRowConversionError::Other { err: err.into() }.into()));
+                    break;
+                }
+            }
+        }
+
+        Ok(ResultSet::new(description, Box::new(rows.into_iter())))
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let sql = render_placeholders(sql);
+        let sqlite_params = params.iter().map(SqliteWormSqlParam);
+        self.0
+            .execute(&sql, sqlite_params)
+            .map(|affected| affected as u64)
+            .map_err(execution_error)
+    }
+
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        self.0.execute_batch(sql).map_err(execution_error)
+    }
+
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        // SQLite transactions are always serializable and can neither be read
+        // only nor deferrable, so asking for anything else is an error rather
+        // than being silently ignored.
+        let unsupported = match options.get_isolation_level() {
+            Some(IsolationLevel::Serializable) | None => None,
+            Some(isolation_level) => Some(format!("the isolation level {}", isolation_level)),
+        }
+        .or_else(|| match options.get_read_only() {
+            Some(true) => Some(String::from("read only transactions")),
+            _ => None,
+        })
+        .or_else(|| match options.get_deferrable() {
+            Some(true) => Some(String::from("deferrable transactions")),
+            _ => None,
+        });
+
+        if let Some(unsupported) = unsupported {
+            let message = format!("SQLite does not support {}", unsupported);
+            return Err(SqlExecutionError::Other {
+                err: message.into(),
+            });
+        }
+
+        self.0.execute_batch("BEGIN").map_err(execution_error)
+    }
+}
+
+fn execution_error(err: SqliteError) -> SqlExecutionError {
+    SqlExecutionError::Other { err: Box::new(err) }
+}
+
+fn convert_row(sqlite_row: &Row, columns: &Arc<[String]>) -> Result<SqlRow, RowConversionError> {
+    let values = (0..columns.len())
+        .map(|i| {
+            let value_ref = sqlite_row
+                .get_raw_checked(i)
+                .map_err(|err| RawRowConversionError::Other { err: err.into() })?;
+
+            match value_ref {
+                ValueRef::Null => Ok(SqlValue::Null),
+                ValueRef::Integer(i) => Ok(SqlValue::Signed64(i)),
+                ValueRef::Real(f) => Ok(SqlValue::Float64(f)),
+                ValueRef::Text(text) => String::from_utf8(text.to_vec())
+                    .map(SqlValue::String)
+                    .map_err(|err| RawRowConversionError::Other { err: err.into() }),
+                ValueRef::Blob(_) => Err(RawRowConversionError::UnsupportedType {
+                    column_name: columns[i].clone(),
+                    type_name: Some(String::from("BLOB")),
+                }),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(SqlRow::new(columns.clone(), values))
+}
+
+/// Converts the positional placeholders used by worm (`$1`) into the numbered
+/// placeholders used by SQLite (`?1`). Placeholders within quoted literals,
+/// identifiers and comments are left as is.
+fn render_placeholders(sql: &str) -> String {
+    let mut rendered = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(open), c) if open == c => quote = None,
+            (None, '-') if chars.peek() == Some(&'-') => {
+                // Quotes within a comment, as in `-- isn't`, open nothing.
+                rendered.push(c);
+                for c in chars.by_ref() {
+                    rendered.push(c);
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            (None, '/') if chars.peek() == Some(&'*') => {
+                rendered.push(c);
+                rendered.extend(chars.next());
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    rendered.push(c);
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+                continue;
+            }
+            (None, '$') if chars.peek().map(char::is_ascii_digit).unwrap_or(false) => {
+                rendered.push('?');
+                continue;
+            }
+            _ => {}
+        }
+
+        rendered.push(c);
+    }
+
+    rendered
+}
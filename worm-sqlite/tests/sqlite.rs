@@ -0,0 +1,268 @@
+//! Integration tests which run against in-memory and temporary SQLite
+//! databases, so they need no server and run with the other tests.
+
+use std::path::PathBuf;
+use worm::connections::Connection;
+use worm::errors::WormError;
+use worm::executors::SqlExecutor;
+use worm::sql::SqlValue;
+use worm::testing::query;
+use worm::testing::run;
+use worm::transactions::IsolationLevel;
+use worm::transactions::TransactionOptions;
+use worm_sqlite::SqliteExecutor;
+
+fn connect(connection_string: &str) -> Connection<SqliteExecutor> {
+    let executor = SqliteExecutor::connect(connection_string).expect("unable to open SQLite");
+    Connection::new(executor)
+}
+
+/// A database file in the temporary directory which is deleted once the test
+/// has finished.
+struct TempDatabase(PathBuf);
+
+impl TempDatabase {
+    fn new(name: &str) -> Self {
+        let file_name = format!("worm-sqlite-{}-{}.db", name, std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+
+    fn connection_string(&self) -> String {
+        format!("sqlite://{}", self.0.display())
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn maps_storage_classes() {
+    let mut connection = connect(":memory:");
+    let rows = query(
+        &mut connection,
+        "SELECT 42 AS i, 1.5 AS r, 'text' AS t, NULL AS n",
+        &[],
+    );
+
+    let row = &rows[0];
+    assert_eq!(row.columns(), ["i", "r", "t", "n"]);
+    assert_eq!(row.get_by_name("i"), Some(&SqlValue::Signed64(42)));
+    assert_eq!(row.get_by_name("r"), Some(&SqlValue::Float64(1.5)));
+    assert_eq!(row.get_by_name("t"), Some(&SqlValue::String("text".into())));
+    assert_eq!(row.get_by_name("n"), Some(&SqlValue::Null));
+}
+
+#[test]
+fn rejects_blobs() {
+    let mut connection = connect(":memory:");
+    let mut rows = connection
+        .query_dynamic("SELECT x'00ff' AS b", &[])
+        .unwrap();
+    assert!(rows.next().unwrap().is_err());
+}
+
+#[test]
+fn binds_parameters() {
+    let mut connection = connect("sqlite::memory:");
+    let rows = query(
+        &mut connection,
+        "SELECT $1 AS b, $2 AS f, $3 AS s, $4 AS u, $5 AS n",
+        &[
+            SqlValue::Boolean(true),
+            SqlValue::Float32(0.5),
+            SqlValue::String("text".into()),
+            SqlValue::Unsigned32(7),
+            SqlValue::Null,
+        ],
+    );
+
+    let values = rows[0].iter().cloned().collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            SqlValue::Signed64(1),
+            SqlValue::Float64(0.5),
+            SqlValue::String("text".into()),
+            SqlValue::Signed64(7),
+            SqlValue::Null,
+        ]
+    );
+}
+
+#[test]
+fn rejects_integers_which_do_not_fit() {
+    let mut connection = connect(":memory:");
+    let result = connection.query_dynamic("SELECT $1", &[SqlValue::Unsigned64(u64::MAX)]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn rewrites_placeholders_outside_of_quotes() {
+    let mut connection = connect(":memory:");
+    let rows = query(
+        &mut connection,
+        "SELECT $2 AS second, '$1' AS literal, $1 AS \"$1\", $10 AS tenth",
+        &[
+            SqlValue::Signed64(1),
+            SqlValue::String("two".into()),
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Null,
+            SqlValue::Signed64(10),
+        ],
+    );
+
+    let row = &rows[0];
+    assert_eq!(row.columns(), ["second", "literal", "$1", "tenth"]);
+    assert_eq!(row.try_get::<String, _>("second").unwrap(), "two");
+    assert_eq!(row.try_get::<String, _>("literal").unwrap(), "$1");
+    assert_eq!(row.try_get::<i64, _>("$1").unwrap(), 1);
+    assert_eq!(row.try_get::<i64, _>("tenth").unwrap(), 10);
+}
+
+#[test]
+fn rewrites_placeholders_after_comments_with_quotes() {
+    let mut connection = connect(":memory:");
+    let rows = query(
+        &mut connection,
+        "SELECT $1 AS first -- the user's id\n\
+            , /* isn't $9 */ $2 AS second",
+        &[SqlValue::Signed64(1), SqlValue::Signed64(2)],
+    );
+
+    let row = &rows[0];
+    assert_eq!(row.try_get::<i64, _>("first").unwrap(), 1);
+    assert_eq!(row.try_get::<i64, _>("second").unwrap(), 2);
+}
+
+#[test]
+fn converts_integers_into_narrower_fields() {
+    let mut connection = connect(":memory:");
+    let rows = query(&mut connection, "SELECT 42 AS id, 300 AS big", &[]);
+
+    let row = &rows[0];
+    assert_eq!(row.try_get::<i32, _>("id").unwrap(), 42);
+    assert!(row.try_get::<u8, _>("big").is_err());
+}
+
+#[test]
+fn describes_declared_column_types() {
+    let mut connection = connect(":memory:");
+    run(
+        &mut connection,
+        "CREATE TABLE users (id INTEGER, name VARCHAR(32))",
+        &[],
+    );
+
+    let results = connection
+        .query_dynamic("SELECT id, name, 1 AS one FROM users", &[])
+        .unwrap();
+    let type_names = results
+        .columns()
+        .iter()
+        .map(|column| column.get_type_name())
+        .collect::<Vec<_>>();
+    assert_eq!(type_names, [Some("INTEGER"), Some("VARCHAR(32)"), None]);
+}
+
+#[test]
+fn keeps_in_memory_databases_private() {
+    let mut first = connect(":memory:");
+    run(&mut first, "CREATE TABLE users (name TEXT)", &[]);
+
+    let mut second = connect(":memory:");
+    let rows = query(
+        &mut second,
+        "SELECT name FROM sqlite_master WHERE name = 'users'",
+        &[],
+    );
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn persists_file_databases() {
+    let database = TempDatabase::new("persists");
+    {
+        let mut connection = connect(&database.connection_string());
+        run(&mut connection, "CREATE TABLE users (name TEXT)", &[]);
+        let affected = connection
+            .executor_mut()
+            .execute_sql_affected(
+                "INSERT INTO users VALUES ($1), ($2)",
+                &[
+                    SqlValue::String("first".into()),
+                    SqlValue::String("second".into()),
+                ],
+            )
+            .unwrap();
+        assert_eq!(affected, 2);
+    }
+
+    let mut connection = connect(&database.connection_string());
+    let rows = query(&mut connection, "SELECT name FROM users ORDER BY name", &[]);
+    let names = rows
+        .iter()
+        .map(|row| row.try_get::<String, _>(0usize).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["first", "second"]);
+}
+
+#[test]
+fn rolls_back_transactions() {
+    let mut connection = connect(":memory:");
+    run(&mut connection, "CREATE TABLE users (name TEXT)", &[]);
+
+    let result: Result<(), WormError> = connection.transaction(|tx| {
+        run(
+            tx,
+            "INSERT INTO users VALUES ($1)",
+            &[SqlValue::String("kept".into())],
+        );
+        let nested: Result<(), WormError> = tx.transaction(|tx| {
+            run(
+                tx,
+                "INSERT INTO users VALUES ($1)",
+                &[SqlValue::String("discarded".into())],
+            );
+            Err(worm::errors::CardinalityError::NoRows.into())
+        });
+        assert!(nested.is_err());
+        Ok(())
+    });
+    result.unwrap();
+
+    let rows = query(&mut connection, "SELECT name FROM users", &[]);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].try_get::<String, _>(0usize).unwrap(), "kept");
+}
+
+#[test]
+fn rejects_unsupported_transaction_options() {
+    let mut connection = connect(":memory:");
+    let unsupported = [
+        TransactionOptions::new().isolation_level(IsolationLevel::ReadCommitted),
+        TransactionOptions::new().isolation_level(IsolationLevel::RepeatableRead),
+        TransactionOptions::new().read_only(true),
+        TransactionOptions::new().deferrable(true),
+    ];
+
+    for options in &unsupported {
+        let result: Result<(), WormError> = connection.transaction_with(options, |_| Ok(()));
+        assert!(result.is_err(), "{:?} was accepted", options);
+    }
+
+    let serializable = TransactionOptions::new()
+        .isolation_level(IsolationLevel::Serializable)
+        .read_only(false);
+    let result: Result<(), WormError> = connection.transaction_with(&serializable, |_| Ok(()));
+    result.unwrap();
+}
@@ -1,11 +1,37 @@
 //! Types for interacting with a database.
 
+use crate::errors::CardinalityError;
+use crate::errors::SqlExecutionError;
 use crate::errors::WormError;
+#[cfg(feature = "async")]
+use crate::executors::AsyncSqlExecutor;
+use crate::executors::CancelHandle;
 use crate::executors::SqlExecutor;
 #[cfg(feature = "rocket-support")]
 use crate::pooling::ConnectionManager;
+#[cfg(feature = "async")]
+use crate::results::AsyncQueryResults;
+use crate::results::CursorResults;
+use crate::results::MultiQueryResults;
 use crate::results::QueryResults;
+use crate::sql::SqlResult;
+use crate::sql::SqlResultSets;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use crate::transactions::Transaction;
+use crate::transactions::TransactionOptions;
+use crate::Returns;
 use crate::Script;
+use crate::ScriptInfo;
+use core::time::Duration;
+#[cfg(feature = "async")]
+use futures_util::future::FutureExt;
+#[cfg(feature = "async")]
+use futures_util::future::LocalBoxFuture;
+#[cfg(feature = "async")]
+use futures_util::stream::StreamExt;
+#[cfg(feature = "async")]
+use futures_util::stream::TryStreamExt;
 #[cfg(feature = "rocket-support")]
 use rocket_contrib::databases::r2d2::Error;
 #[cfg(feature = "rocket-support")]
@@ -14,6 +40,10 @@ use rocket_contrib::databases::r2d2::Pool;
 use rocket_contrib::databases::DatabaseConfig;
 #[cfg(feature = "rocket-support")]
 use rocket_contrib::databases::Poolable;
+#[cfg(feature = "async")]
+use std::panic::resume_unwind;
+#[cfg(feature = "async")]
+use std::panic::AssertUnwindSafe;
 
 /// An active connection with a databse which automatically converts between
 /// SQL types and Rust types.
@@ -23,6 +53,10 @@ where
 {
     /// The executor which connects to the database.
     executor: E,
+    /// The number of transactions which are currently open on the connection.
+    pub(crate) transaction_depth: usize,
+    /// The longest a statement may run, unless its script sets a timeout.
+    timeout: Option<Duration>,
 }
 
 impl<E> Connection<E>
@@ -31,20 +65,537 @@ where
 {
     /// Creates a new connection with the provided executor.
     pub fn new(executor: E) -> Self {
-        Self { executor }
+        Self {
+            executor,
+            transaction_depth: 0,
+            timeout: None,
+        }
+    }
+
+    /// Sets the longest a statement executed through the connection may run
+    /// before the database cancels it. Scripts which set their own timeout
+    /// use it instead.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets or removes the timeout of the statements executed through the
+    /// connection.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Gets the timeout of the statements executed through the connection.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Creates a handle which cancels the statement running on the connection
+    /// from another thread. Returns `None` if the executor does not support
+    /// cancellation.
+    pub fn cancel_handle(&self) -> Option<CancelHandle> {
+        self.executor.cancel_handle()
+    }
+
+    /// Applies the timeout of the script and tells the executor which script
+    /// the next statement was compiled from.
+    pub(crate) fn start_script(&mut self, script: &ScriptInfo) -> Result<(), SqlExecutionError> {
+        self.executor
+            .set_timeout(script.timeout().or(self.timeout))?;
+        self.executor.on_script(script);
+        Ok(())
+    }
+
+    /// Gives access to the executor, for features which are specific to a
+    /// DBMS. Statements run through it bypass the checks of the connection.
+    pub fn executor_mut(&mut self) -> &mut E {
+        &mut self.executor
+    }
+
+    /// Runs the provided closure within a transaction. The transaction is
+    /// committed if the closure returns `Ok` and rolled back if it returns
+    /// `Err` or panics. If the connection is already within a transaction, a
+    /// savepoint is used instead.
+    pub fn transaction<F, T, Er>(&mut self, f: F) -> Result<T, Er>
+    where
+        F: FnOnce(&mut Transaction<'_, E>) -> Result<T, Er>,
+        Er: From<WormError>,
+    {
+        self.transaction_with(&TransactionOptions::default(), f)
+    }
+
+    /// Runs the provided closure within a transaction with the provided
+    /// options. The options are ignored if a savepoint is used.
+    pub fn transaction_with<F, T, Er>(
+        &mut self,
+        options: &TransactionOptions,
+        f: F,
+    ) -> Result<T, Er>
+    where
+        F: FnOnce(&mut Transaction<'_, E>) -> Result<T, Er>,
+        Er: From<WormError>,
+    {
+        let mut transaction = Transaction::begin(self, options).map_err(WormError::from)?;
+        match f(&mut transaction) {
+            Ok(value) => {
+                transaction.commit().map_err(WormError::from)?;
+                Ok(value)
+            }
+            Err(err) => {
+                // The original error is more useful than a failed rollback.
+                let _ = transaction.rollback();
+                Err(err)
+            }
+        }
     }
 
     /// Executes a provided script.
-    pub fn execute<S>(&mut self, script: S) -> Result<QueryResults<S::Output>, WormError>
+    pub fn execute<S>(&mut self, script: S) -> Result<QueryResults<'_, S::Output>, WormError>
+    where
+        S: Script,
+    {
+        check_result_sets::<S>()?;
+        let sql = script.compile();
+        self.start_script(&ScriptInfo::of::<S>())?;
+        let row_iter = self.executor.execute_sql(&sql, &[])?;
+        Ok(QueryResults::new(row_iter))
+    }
+
+    /// Executes a script with several statements, in order, and returns the
+    /// result set of each statement which returns rows. The output of the
+    /// script is a tuple with a type for each set. Unless the script runs
+    /// within a transaction, the statements before a failing statement keep
+    /// their effects.
+    pub fn execute_multi<S>(&mut self, script: S) -> Result<MultiQueryResults<S::Output>, WormError>
+    where
+        S: Script,
+        S::Output: SqlResultSets,
+    {
+        let sql = script.compile();
+        self.start_script(&ScriptInfo::of::<S>())?;
+        let sets = self.executor.execute_sql_multi(&sql)?;
+        let expected = <S::Output as SqlResultSets>::COUNT;
+        if sets.len() != expected {
+            return Err(CardinalityError::ResultSets {
+                expected,
+                returned: sets.len(),
+            }
+            .into());
+        }
+
+        Ok(MultiQueryResults::new(sets))
+    }
+
+    /// Executes a script with several statements and collects the records of
+    /// each of its result sets.
+    pub fn fetch_all_multi<S>(
+        &mut self,
+        script: S,
+    ) -> Result<<S::Output as SqlResultSets>::Records, WormError>
+    where
+        S: Script,
+        S::Output: SqlResultSets,
+    {
+        let records = self.execute_multi(script)?.collect_sets()?;
+        Ok(records)
+    }
+
+    /// Executes a script through a server-side cursor which fetches
+    /// `fetch_size` rows at a time, so that memory use does not grow with the
+    /// number of rows returned. The cursor is closed when the results are
+    /// dropped, even if not every row was read.
+    ///
+    /// This requires a DBMS which supports `DECLARE ... CURSOR`, like
+    /// postgres.
+    pub fn execute_cursor<S>(
+        &mut self,
+        script: S,
+        fetch_size: usize,
+    ) -> Result<CursorResults<'_, E, S::Output>, WormError>
     where
         S: Script,
     {
+        check_result_sets::<S>()?;
         let sql = script.compile();
-        let row_iter = self.executor.execute_sql(&sql)?;
+        Ok(CursorResults::declare(
+            self,
+            &ScriptInfo::of::<S>(),
+            &sql,
+            fetch_size,
+        )?)
+    }
+
+    /// Executes a script which returns exactly one row.
+    pub fn fetch_one<S>(&mut self, script: S) -> Result<S::Output, WormError>
+    where
+        S: Script,
+    {
+        let mut results = self.fetch::<S>(script, Returns::One)?;
+        let first = results.next().ok_or(CardinalityError::NoRows)??;
+        match results.next() {
+            Some(_) => Err(CardinalityError::TooManyRows.into()),
+            None => Ok(first),
+        }
+    }
+
+    /// Executes a script which returns at most one row.
+    pub fn fetch_optional<S>(&mut self, script: S) -> Result<Option<S::Output>, WormError>
+    where
+        S: Script,
+    {
+        let mut results = self.fetch::<S>(script, Returns::Optional)?;
+        let first = match results.next() {
+            Some(first) => first?,
+            None => return Ok(None),
+        };
+
+        match results.next() {
+            Some(_) => Err(CardinalityError::TooManyRows.into()),
+            None => Ok(Some(first)),
+        }
+    }
+
+    /// Executes a script and collects all of the rows it returns.
+    pub fn fetch_all<S>(&mut self, script: S) -> Result<Vec<S::Output>, WormError>
+    where
+        S: Script,
+    {
+        let results = self.fetch::<S>(script, Returns::Many)?;
+        let records = results.collect::<Result<_, _>>()?;
+        Ok(records)
+    }
+
+    /// Executes a script for its side effects and returns the number of rows
+    /// it affected.
+    pub fn execute_affected<S>(&mut self, script: S) -> Result<u64, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let sql = script.compile();
+        self.start_script(&ScriptInfo::of::<S>())?;
+        let affected = self.executor.execute_sql_affected(&sql, &[])?;
+        Ok(affected)
+    }
+
+    /// Executes a batch of scripts for their side effects and returns the
+    /// number of rows each script affected. A script which fails does not stop
+    /// the scripts after it.
+    pub fn execute_batch<I, S>(
+        &mut self,
+        scripts: I,
+    ) -> Result<Vec<Result<u64, WormError>>, WormError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let statements = scripts.into_iter().map(S::compile).collect::<Vec<_>>();
+        self.start_script(&ScriptInfo::of::<S>())?;
+        let results = self.executor.execute_sql_batch(&statements);
+        Ok(results
+            .into_iter()
+            .map(|result| result.map_err(WormError::from))
+            .collect())
+    }
+
+    /// Executes a batch of scripts within a transaction, so that either every
+    /// script takes effect or, if any of them fails, none of them do. Returns
+    /// the number of rows each script affected.
+    pub fn execute_batch_atomic<I, S>(&mut self, scripts: I) -> Result<Vec<u64>, WormError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let statements = scripts.into_iter().map(S::compile).collect::<Vec<_>>();
+        self.transaction(|transaction| {
+            transaction.start_script(&ScriptInfo::of::<S>())?;
+            let affected = transaction.executor.execute_sql_batch_atomic(&statements)?;
+            Ok(affected)
+        })
+    }
+
+    fn fetch<S>(
+        &mut self,
+        script: S,
+        requested: Returns,
+    ) -> Result<QueryResults<'_, S::Output>, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(requested)?;
+        self.execute(script)
+    }
+
+    /// Executes SQL that is not known until runtime. The values of `params`
+    /// are bound to the positional placeholders (`$1`, `$2`, ...) within the
+    /// SQL and the rows are returned as is, with their column names.
+    pub fn query_dynamic(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<QueryResults<'_, SqlRow>, WormError> {
+        self.executor.set_timeout(self.timeout)?;
+        let row_iter = self.executor.execute_sql(sql, params)?;
         Ok(QueryResults::new(row_iter))
     }
 }
 
+/// An active connection with a database which is driven asynchronously. It
+/// offers the same [Script] based API as [Connection].
+#[cfg(feature = "async")]
+pub struct AsyncConnection<E>
+where
+    E: AsyncSqlExecutor,
+{
+    /// The executor which connects to the database.
+    executor: E,
+    /// The number of transactions which are open on the connection.
+    transaction_depth: usize,
+}
+
+#[cfg(feature = "async")]
+impl<E> AsyncConnection<E>
+where
+    E: AsyncSqlExecutor,
+{
+    /// Creates a new connection with the provided executor.
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor,
+            transaction_depth: 0,
+        }
+    }
+
+    /// Runs the future returned by the provided closure within a transaction.
+    /// The transaction is committed if the future resolves to `Ok` and rolled
+    /// back if it resolves to `Err` or panics. If the connection is already
+    /// within a transaction, a savepoint is used instead.
+    pub async fn transaction<F, T, Er>(&mut self, f: F) -> Result<T, Er>
+    where
+        F: for<'t> FnOnce(&'t mut Self) -> LocalBoxFuture<'t, Result<T, Er>>,
+        Er: From<WormError>,
+    {
+        self.transaction_with(&TransactionOptions::default(), f)
+            .await
+    }
+
+    /// Runs the future returned by the provided closure within a transaction
+    /// with the provided options. The options are ignored if a savepoint is
+    /// used.
+    pub async fn transaction_with<F, T, Er>(
+        &mut self,
+        options: &TransactionOptions,
+        f: F,
+    ) -> Result<T, Er>
+    where
+        F: for<'t> FnOnce(&'t mut Self) -> LocalBoxFuture<'t, Result<T, Er>>,
+        Er: From<WormError>,
+    {
+        let savepoint = if self.transaction_depth == 0 {
+            self.executor
+                .begin_transaction(options)
+                .await
+                .map_err(WormError::from)?;
+            None
+        } else {
+            let name = format!("worm_savepoint_{}", self.transaction_depth);
+            self.executor
+                .create_savepoint(&name)
+                .await
+                .map_err(WormError::from)?;
+            Some(name)
+        };
+
+        self.transaction_depth += 1;
+        let result = AssertUnwindSafe(f(self)).catch_unwind().await;
+        self.transaction_depth -= 1;
+
+        match result {
+            Ok(Ok(value)) => {
+                self.finish_transaction(savepoint.as_deref(), true)
+                    .await
+                    .map_err(WormError::from)?;
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                // The original error is more useful than a failed rollback.
+                let _ = self.finish_transaction(savepoint.as_deref(), false).await;
+                Err(err)
+            }
+            Err(panic) => {
+                let _ = self.finish_transaction(savepoint.as_deref(), false).await;
+                resume_unwind(panic)
+            }
+        }
+    }
+
+    /// Commits or rolls back the innermost transaction, or releases or rolls
+    /// back to its savepoint if it is nested.
+    async fn finish_transaction(
+        &mut self,
+        savepoint: Option<&str>,
+        commit: bool,
+    ) -> Result<(), SqlExecutionError> {
+        match (savepoint, commit) {
+            (None, true) => self.executor.commit_transaction().await,
+            (None, false) => self.executor.rollback_transaction().await,
+            (Some(name), true) => self.executor.release_savepoint(name).await,
+            (Some(name), false) => {
+                self.executor.rollback_to_savepoint(name).await?;
+                self.executor.release_savepoint(name).await
+            }
+        }
+    }
+
+    /// Executes a provided script.
+    pub async fn execute<S>(
+        &mut self,
+        script: S,
+    ) -> Result<AsyncQueryResults<'_, S::Output>, WormError>
+    where
+        S: Script,
+    {
+        check_result_sets::<S>()?;
+        let sql = script.compile();
+        self.executor.on_script(&ScriptInfo::of::<S>());
+        let row_stream = self.executor.execute_sql(&sql, &[]).await?;
+        Ok(AsyncQueryResults::new(row_stream))
+    }
+
+    /// Executes a script which returns exactly one row.
+    pub async fn fetch_one<S>(&mut self, script: S) -> Result<S::Output, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(Returns::One)?;
+        let mut results = self.execute(script).await?;
+        let first = results.next().await.ok_or(CardinalityError::NoRows)??;
+        match results.next().await {
+            Some(_) => Err(CardinalityError::TooManyRows.into()),
+            None => Ok(first),
+        }
+    }
+
+    /// Executes a script which returns at most one row.
+    pub async fn fetch_optional<S>(&mut self, script: S) -> Result<Option<S::Output>, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(Returns::Optional)?;
+        let mut results = self.execute(script).await?;
+        let first = match results.next().await {
+            Some(first) => first?,
+            None => return Ok(None),
+        };
+
+        match results.next().await {
+            Some(_) => Err(CardinalityError::TooManyRows.into()),
+            None => Ok(Some(first)),
+        }
+    }
+
+    /// Executes a script and collects all of the rows it returns.
+    pub async fn fetch_all<S>(&mut self, script: S) -> Result<Vec<S::Output>, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(Returns::Many)?;
+        let results = self.execute(script).await?;
+        let records = results.try_collect().await?;
+        Ok(records)
+    }
+
+    /// Executes a script for its side effects and returns the number of rows
+    /// it affected.
+    pub async fn execute_affected<S>(&mut self, script: S) -> Result<u64, WormError>
+    where
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let sql = script.compile();
+        self.executor.on_script(&ScriptInfo::of::<S>());
+        let affected = self.executor.execute_sql_affected(&sql, &[]).await?;
+        Ok(affected)
+    }
+
+    /// Executes a batch of scripts for their side effects and returns the
+    /// number of rows each script affected. A script which fails does not stop
+    /// the scripts after it.
+    pub async fn execute_batch<I, S>(
+        &mut self,
+        scripts: I,
+    ) -> Result<Vec<Result<u64, WormError>>, WormError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let statements = scripts.into_iter().map(S::compile).collect::<Vec<_>>();
+        self.executor.on_script(&ScriptInfo::of::<S>());
+        let results = self.executor.execute_sql_batch(&statements).await;
+        Ok(results
+            .into_iter()
+            .map(|result| result.map_err(WormError::from))
+            .collect())
+    }
+
+    /// Executes a batch of scripts within a transaction, so that either every
+    /// script takes effect or, if any of them fails, none of them do. Returns
+    /// the number of rows each script affected.
+    pub async fn execute_batch_atomic<I, S>(&mut self, scripts: I) -> Result<Vec<u64>, WormError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Script,
+    {
+        check_returns::<S>(Returns::None)?;
+        let statements = scripts.into_iter().map(S::compile).collect::<Vec<_>>();
+        self.executor
+            .begin_transaction(&TransactionOptions::default())
+            .await?;
+        self.executor.on_script(&ScriptInfo::of::<S>());
+        match self.executor.execute_sql_batch_atomic(&statements).await {
+            Ok(affected) => {
+                self.executor.commit_transaction().await?;
+                Ok(affected)
+            }
+            Err(err) => {
+                // The original error is more useful than a failed rollback.
+                let _ = self.executor.rollback_transaction().await;
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Executes SQL that is not known until runtime. The values of `params`
+    /// are bound to the positional placeholders (`$1`, `$2`, ...) within the
+    /// SQL and the rows are returned as is, with their column names.
+    pub async fn query_dynamic(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<AsyncQueryResults<'_, SqlRow>, WormError> {
+        let row_stream = self.executor.execute_sql(sql, params).await?;
+        Ok(AsyncQueryResults::new(row_stream))
+    }
+}
+
+fn check_returns<S>(requested: Returns) -> Result<(), CardinalityError>
+where
+    S: Script,
+{
+    if S::RETURNS.allows(requested) {
+        Ok(())
+    } else {
+        Err(CardinalityError::Mismatch {
+            declared: S::RETURNS,
+            requested,
+        })
+    }
+}
+
 #[cfg(feature = "rocket-support")]
 impl<E> Poolable for Connection<E>
 where
@@ -59,3 +610,14 @@ where
         Pool::builder().max_size(config.pool_size).build(manager)
     }
 }
+
+/// Checks that the output of the script is read from a single result set.
+fn check_result_sets<S>() -> Result<(), CardinalityError>
+where
+    S: Script,
+{
+    match <S::Output as SqlResult>::RESULT_SETS {
+        1 => Ok(()),
+        expected => Err(CardinalityError::MultipleResultSets { expected }),
+    }
+}
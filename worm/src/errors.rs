@@ -1,5 +1,8 @@
 //! Types for representing errors in the worm framework.
 
+#[cfg(feature = "cassettes")]
+use crate::sql::SqlValue;
+use crate::Returns;
 use core::fmt::Display;
 use core::fmt::Formatter;
 use core::fmt::Result as FmtResult;
@@ -16,6 +19,9 @@ pub enum WormError {
     /// An error that occurs while converting the DBMS specific row
     /// representation to the user-defined record type.
     RowConversionError(RowConversionError),
+    /// An error that occurs when a script returns a different number of rows
+    /// than was expected.
+    CardinalityError(CardinalityError),
 }
 
 impl Display for WormError {
@@ -34,6 +40,7 @@ impl Display for WormError {
                     conversion_error
                 )
             }
+            WormError::CardinalityError(cardinality_err) => cardinality_err.fmt(f),
         }
     }
 }
@@ -44,6 +51,7 @@ impl Error for WormError {
             WormError::ConnectionError(conn_err) => Some(conn_err),
             WormError::SqlExecutionError(sql_err) => Some(sql_err),
             WormError::RowConversionError(conversion_err) => Some(conversion_err),
+            WormError::CardinalityError(cardinality_err) => Some(cardinality_err),
         }
     }
 }
@@ -66,16 +74,97 @@ impl From<RowConversionError> for WormError {
     }
 }
 
+impl From<CardinalityError> for WormError {
+    fn from(err: CardinalityError) -> Self {
+        Self::CardinalityError(err)
+    }
+}
+
+/// An error that occurs when a script returns a different number of rows than
+/// was expected.
+#[derive(Debug, Eq, PartialEq)]
+pub enum CardinalityError {
+    /// A row was expected but the script returned none.
+    NoRows,
+    /// At most one row was expected but the script returned more.
+    TooManyRows,
+    /// The script was fetched in a way its declared cardinality does not
+    /// allow.
+    Mismatch {
+        /// The cardinality declared by the script.
+        declared: Returns,
+        /// The cardinality the script was fetched with.
+        requested: Returns,
+    },
+    /// A script with several statements returned a different number of
+    /// result sets than its output has types.
+    ResultSets {
+        /// The number of types of the output of the script.
+        expected: usize,
+        /// The number of result sets the script returned.
+        returned: usize,
+    },
+    /// A script whose output is read from several result sets was executed
+    /// as a single result set.
+    MultipleResultSets {
+        /// The number of result sets the output of the script is read from.
+        expected: usize,
+    },
+}
+
+impl Display for CardinalityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            CardinalityError::NoRows => write!(f, "The script returned no rows"),
+            CardinalityError::TooManyRows => {
+                write!(f, "The script returned more than one row")
+            }
+            CardinalityError::Mismatch {
+                declared,
+                requested,
+            } => write!(
+                f,
+                "A script which returns {} rows cannot be fetched as {}",
+                declared, requested
+            ),
+            CardinalityError::ResultSets { expected, returned } => write!(
+                f,
+                "The script was expected to return {} result sets but returned {}",
+                expected, returned
+            ),
+            CardinalityError::MultipleResultSets { expected } => write!(
+                f,
+                "The output of the script is read from {} result sets, so the script must be executed with execute_multi",
+                expected
+            ),
+        }
+    }
+}
+
+impl Error for CardinalityError {}
+
 /// An error that occurs while connecting to a specified database.
 #[derive(Debug)]
 pub struct ConnectionError {
     connection_string: String,
+    err: Option<Box<dyn Error>>,
 }
 
 impl ConnectionError {
     /// Creates a new connection error.
     pub fn new(connection_string: String) -> Self {
-        Self { connection_string }
+        Self {
+            connection_string,
+            err: None,
+        }
+    }
+
+    /// Creates a new connection error which was caused by another error.
+    pub fn with_source(connection_string: String, err: Box<dyn Error>) -> Self {
+        Self {
+            connection_string,
+            err: Some(err),
+        }
     }
 }
 
@@ -85,11 +174,20 @@ impl Display for ConnectionError {
             f,
             "There was a problem connecting to the databse specified by {}",
             &self.connection_string
-        )
+        )?;
+
+        match &self.err {
+            Some(err) => write!(f, ": {}", err),
+            None => Ok(()),
+        }
     }
 }
 
-impl Error for ConnectionError {}
+impl Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.err.as_deref()
+    }
+}
 
 /// An error that occurs while executing SQL generated by worm.
 #[derive(Debug)]
@@ -100,6 +198,11 @@ pub enum SqlExecutionError {
         /// The underlying error.
         err: Box<dyn Error>,
     },
+    /// The SQL was cancelled through a
+    /// [CancelHandle](crate::executors::CancelHandle) before it finished.
+    Cancelled,
+    /// The SQL was cancelled because it ran for longer than its timeout.
+    TimedOut,
 }
 
 impl Display for SqlExecutionError {
@@ -108,6 +211,12 @@ impl Display for SqlExecutionError {
             SqlExecutionError::Other { err } => {
                 write!(f, "There was a problem executing the SQL: {}", err)
             }
+            SqlExecutionError::Cancelled => {
+                write!(f, "The SQL was cancelled before it finished")
+            }
+            SqlExecutionError::TimedOut => {
+                write!(f, "The SQL was cancelled because it exceeded its timeout")
+            }
         }
     }
 }
@@ -116,6 +225,7 @@ impl Error for SqlExecutionError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             SqlExecutionError::Other { err } => Some(err.as_ref()),
+            SqlExecutionError::Cancelled | SqlExecutionError::TimedOut => None,
         }
     }
 }
@@ -141,9 +251,17 @@ pub enum RowConversionError {
         /// The field name for which the value was missing.
         field_name: &'static str,
     },
+    /// Occurs when a requested column does not exist within the row.
+    ColumnNotFound {
+        /// The name or position of the column that was requested.
+        column: String,
+    },
     /// An error that occurs while converting a field's [SqlValue] into a
     /// concrete Rust type.
     FieldConversionError(FieldConversionError),
+    /// An error that occurs while deserializing a row through serde.
+    #[cfg(feature = "serde")]
+    DeserializationError(DeserializationError),
 }
 
 impl Display for RowConversionError {
@@ -153,7 +271,12 @@ impl Display for RowConversionError {
             RowConversionError::MissingFieldValue { field_name } => {
                 write!(f, "There was no value for the field '{}'", field_name)
             }
+            RowConversionError::ColumnNotFound { column } => {
+                write!(f, "The row has no column '{}'", column)
+            }
             RowConversionError::FieldConversionError(field_err) => field_err.fmt(f),
+            #[cfg(feature = "serde")]
+            RowConversionError::DeserializationError(de_err) => de_err.fmt(f),
         }
     }
 }
@@ -163,7 +286,10 @@ impl Error for RowConversionError {
         match self {
             RowConversionError::RawRowConversionFailure(raw_err) => Some(raw_err),
             RowConversionError::MissingFieldValue { .. } => None,
+            RowConversionError::ColumnNotFound { .. } => None,
             RowConversionError::FieldConversionError(field_err) => Some(field_err),
+            #[cfg(feature = "serde")]
+            RowConversionError::DeserializationError(de_err) => Some(de_err),
         }
     }
 }
@@ -180,6 +306,13 @@ impl From<FieldConversionError> for RowConversionError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl From<DeserializationError> for RowConversionError {
+    fn from(err: DeserializationError) -> Self {
+        Self::DeserializationError(err)
+    }
+}
+
 /// An error that occurs while translating a DBMS specific row representation
 /// into a [SqlRow].
 #[derive(Debug)]
@@ -255,3 +388,124 @@ impl Display for FieldConversionError {
 }
 
 impl Error for FieldConversionError {}
+
+/// An error that occurs while deserializing a [SqlRow] through serde.
+#[cfg(feature = "serde")]
+#[derive(Debug, Eq, PartialEq)]
+pub struct DeserializationError {
+    message: String,
+}
+
+#[cfg(feature = "serde")]
+impl DeserializationError {
+    /// Creates a new deserialization error with the provided message.
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Display for DeserializationError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Unable to deserialize the row: {}", self.message)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for DeserializationError {}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for DeserializationError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Self::new(msg.to_string())
+    }
+}
+
+/// An error that occurs while recording or replaying a cassette.
+#[cfg(feature = "cassettes")]
+#[derive(Debug)]
+pub enum CassetteError {
+    /// The cassette file could not be read or written.
+    Io(std::io::Error),
+    /// The cassette file does not contain a valid cassette.
+    Format(serde_json::Error),
+    /// A statement was executed after every recorded statement was replayed.
+    Unrecorded {
+        /// The SQL which was executed.
+        sql: String,
+    },
+    /// A statement was executed which differs from the statement that was
+    /// recorded at the same point.
+    Mismatch {
+        /// The SQL which was recorded.
+        expected_sql: String,
+        /// The parameters which were recorded.
+        expected_params: Vec<SqlValue>,
+        /// The SQL which was executed.
+        sql: String,
+        /// The parameters which were bound to the executed SQL.
+        params: Vec<SqlValue>,
+    },
+}
+
+#[cfg(feature = "cassettes")]
+impl Display for CassetteError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            CassetteError::Io(io_err) => write!(f, "Unable to access the cassette: {}", io_err),
+            CassetteError::Format(format_err) => {
+                write!(f, "The cassette is not valid: {}", format_err)
+            }
+            CassetteError::Unrecorded { sql } => write!(
+                f,
+                "The statement '{}' was executed after the end of the cassette.",
+                sql
+            ),
+            CassetteError::Mismatch {
+                expected_sql,
+                expected_params,
+                sql,
+                params,
+            } => write!(
+                f,
+                "The statement '{}' with the parameters {:?} was executed, but the cassette recorded '{}' with the parameters {:?}.",
+                sql, params, expected_sql, expected_params
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "cassettes")]
+impl Error for CassetteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CassetteError::Io(io_err) => Some(io_err),
+            CassetteError::Format(format_err) => Some(format_err),
+            CassetteError::Unrecorded { .. } | CassetteError::Mismatch { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "cassettes")]
+impl From<std::io::Error> for CassetteError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "cassettes")]
+impl From<serde_json::Error> for CassetteError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Format(err)
+    }
+}
+
+#[cfg(feature = "cassettes")]
+impl From<CassetteError> for SqlExecutionError {
+    fn from(err: CassetteError) -> Self {
+        Self::Other { err: Box::new(err) }
+    }
+}
@@ -3,11 +3,72 @@
 use crate::errors::ConnectionError;
 use crate::errors::RowConversionError;
 use crate::errors::SqlExecutionError;
+use crate::sql::split_statements;
+use crate::sql::ColumnDescription;
 use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use crate::transactions::TransactionOptions;
+use crate::ScriptInfo;
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+use core::time::Duration;
+#[cfg(feature = "async")]
+use futures_core::Stream;
+use std::sync::Arc;
 
-/// The iterator that is returned by an executor.
+/// The iterator over the rows of a result set.
 pub type ResultIter<'a> = Box<dyn Iterator<Item = Result<SqlRow, RowConversionError>> + 'a>;
 
+/// The rows returned by an executor along with the description of their
+/// columns. The description is empty if the executor does not report one.
+pub struct ResultSet<'a> {
+    columns: Arc<[ColumnDescription]>,
+    rows: ResultIter<'a>,
+}
+
+impl<'a> ResultSet<'a> {
+    /// Creates a result set from the description of its columns and its rows.
+    pub fn new(columns: Arc<[ColumnDescription]>, rows: ResultIter<'a>) -> Self {
+        Self { columns, rows }
+    }
+
+    /// The descriptions of the columns, in the order of the values of each
+    /// row.
+    pub fn columns(&self) -> &[ColumnDescription] {
+        &self.columns
+    }
+
+    /// Consumes the result set, returning the descriptions of its columns.
+    pub fn into_columns(self) -> Arc<[ColumnDescription]> {
+        self.columns
+    }
+
+    pub(crate) fn into_parts(self) -> (Arc<[ColumnDescription]>, ResultIter<'a>) {
+        (self.columns, self.rows)
+    }
+}
+
+impl<'a> From<ResultIter<'a>> for ResultSet<'a> {
+    fn from(rows: ResultIter<'a>) -> Self {
+        Self::new(Arc::from(Vec::new()), rows)
+    }
+}
+
+impl<'a> Iterator for ResultSet<'a> {
+    type Item = Result<SqlRow, RowConversionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+/// The stream that is returned by an asynchronous executor.
+#[cfg(feature = "async")]
+pub type ResultStream<'a> =
+    Pin<Box<dyn Stream<Item = Result<SqlRow, RowConversionError>> + Send + 'a>>;
+
 /// A type which can execute SQL.
 pub trait SqlExecutor {
     /// Creates a SqlExecutor with the provided connection string.
@@ -15,6 +76,272 @@ pub trait SqlExecutor {
     where
         Self: Sized;
 
-    /// Executes the supplied script
-    fn execute_sql<'a>(&'a mut self, sql: &str) -> Result<ResultIter<'a>, SqlExecutionError>;
+    /// Executes the supplied script. The values of `params` are bound to the
+    /// positional placeholders (`$1`, `$2`, ...) within the SQL. The rows are
+    /// returned along with the description of their columns.
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError>;
+
+    /// Executes the supplied script and returns the number of rows it
+    /// affected.
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError>;
+
+    /// Executes several statements which take no parameters and returns the
+    /// number of rows each of them affected. A statement which fails does not
+    /// stop the statements after it. Executors may send the statements
+    /// together to save round trips.
+    fn execute_sql_batch(&mut self, statements: &[String]) -> Vec<Result<u64, SqlExecutionError>> {
+        statements
+            .iter()
+            .map(|sql| self.execute_sql_affected(sql, &[]))
+            .collect()
+    }
+
+    /// Executes several statements which take no parameters within the
+    /// current transaction and returns the number of rows each of them
+    /// affected. The batch stops at the first statement which fails.
+    fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        statements
+            .iter()
+            .map(|sql| self.execute_sql_affected(sql, &[]))
+            .collect()
+    }
+
+    /// Executes SQL which may contain several statements separated by
+    /// semicolons, such as a migration. The SQL takes no parameters and any
+    /// rows it returns are discarded.
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(sql, &[]).map(|_| ())
+    }
+
+    /// Executes SQL which may contain several statements, in order, and
+    /// returns the result set of each statement which returns rows. The SQL
+    /// takes no parameters. The rows are read up front, since the statements
+    /// after a result set run before it is returned.
+    ///
+    /// By default, the SQL is split with [split_statements] and a statement
+    /// returns rows if its result set describes columns or holds rows.
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        execute_split(self, sql)
+    }
+
+    /// Notifies the executor that the next statement it executes was compiled
+    /// from the described script. Executors may use this to attribute the
+    /// statement to its script.
+    fn on_script(&mut self, _: &ScriptInfo) {}
+
+    /// Sets the longest the statements executed after this may run before the
+    /// database cancels them. `None` removes the limit. Executors which
+    /// cannot limit statements only support `None`.
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SqlExecutionError> {
+        match timeout {
+            Some(_) => Err(SqlExecutionError::Other {
+                err: "The executor does not support statement timeouts".into(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates a handle which cancels the statement the executor is running
+    /// from another thread, if the executor supports cancellation.
+    fn cancel_handle(&self) -> Option<CancelHandle> {
+        None
+    }
+
+    /// Begins a transaction with the provided options.
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&options.begin_sql(), &[])
+            .map(|_| ())
+    }
+
+    /// Commits the current transaction.
+    fn commit_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected("COMMIT", &[]).map(|_| ())
+    }
+
+    /// Rolls back the current transaction.
+    fn rollback_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected("ROLLBACK", &[]).map(|_| ())
+    }
+
+    /// Creates a savepoint with the provided name within the current
+    /// transaction.
+    fn create_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("SAVEPOINT {}", name), &[])
+            .map(|_| ())
+    }
+
+    /// Releases the savepoint with the provided name, keeping its changes.
+    fn release_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("RELEASE SAVEPOINT {}", name), &[])
+            .map(|_| ())
+    }
+
+    /// Discards the changes made since the savepoint with the provided name
+    /// was created.
+    fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("ROLLBACK TO SAVEPOINT {}", name), &[])
+            .map(|_| ())
+    }
+}
+
+/// Splits SQL into statements which are executed one at a time, returning the
+/// result set of each statement which describes columns or holds rows.
+pub(crate) fn execute_split<E>(
+    executor: &mut E,
+    sql: &str,
+) -> Result<Vec<ResultSet<'static>>, SqlExecutionError>
+where
+    E: SqlExecutor + ?Sized,
+{
+    let mut sets = Vec::new();
+    for statement in split_statements(sql) {
+        let mut result_set = executor.execute_sql(statement, &[])?;
+        let rows = result_set.by_ref().collect::<Vec<_>>();
+        let columns = result_set.into_columns();
+        if !columns.is_empty() || !rows.is_empty() {
+            sets.push(ResultSet::new(columns, Box::new(rows.into_iter())));
+        }
+    }
+
+    Ok(sets)
+}
+
+/// A type which cancels the statement running on an executor.
+pub trait Cancel: Send + Sync {
+    /// Asks the database to cancel the running statement, if there is one.
+    /// The cancelled statement fails with [SqlExecutionError::Cancelled].
+    fn cancel(&self) -> Result<(), SqlExecutionError>;
+}
+
+/// A handle which cancels the statement running on a connection from another
+/// thread. Handles can be cloned and outlive the connection.
+#[derive(Clone)]
+pub struct CancelHandle {
+    canceller: Arc<dyn Cancel>,
+}
+
+impl CancelHandle {
+    /// Creates a handle which cancels statements with `canceller`.
+    pub fn new(canceller: impl Cancel + 'static) -> Self {
+        Self {
+            canceller: Arc::new(canceller),
+        }
+    }
+
+    /// Asks the database to cancel the running statement, if there is one.
+    pub fn cancel(&self) -> Result<(), SqlExecutionError> {
+        self.canceller.cancel()
+    }
+}
+
+/// A type which can execute SQL asynchronously.
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncSqlExecutor: Send {
+    /// Creates an AsyncSqlExecutor with the provided connection string.
+    async fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized;
+
+    /// Executes the supplied script. The values of `params` are bound to the
+    /// positional placeholders (`$1`, `$2`, ...) within the SQL.
+    async fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultStream<'a>, SqlExecutionError>;
+
+    /// Executes the supplied script and returns the number of rows it
+    /// affected.
+    async fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError>;
+
+    /// Executes several statements which take no parameters and returns the
+    /// number of rows each of them affected. A statement which fails does not
+    /// stop the statements after it. Executors may pipeline the statements to
+    /// save round trips.
+    async fn execute_sql_batch(
+        &mut self,
+        statements: &[String],
+    ) -> Vec<Result<u64, SqlExecutionError>>;
+
+    /// Executes several statements which take no parameters within the
+    /// current transaction and returns the number of rows each of them
+    /// affected. The batch stops at the first statement which fails.
+    async fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        let mut affected = Vec::with_capacity(statements.len());
+        for sql in statements {
+            affected.push(self.execute_sql_affected(sql, &[]).await?);
+        }
+
+        Ok(affected)
+    }
+
+    /// Begins a transaction with the provided options.
+    async fn begin_transaction(
+        &mut self,
+        options: &TransactionOptions,
+    ) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&options.begin_sql(), &[])
+            .await
+            .map(|_| ())
+    }
+
+    /// Commits the current transaction.
+    async fn commit_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected("COMMIT", &[]).await.map(|_| ())
+    }
+
+    /// Rolls back the current transaction.
+    async fn rollback_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected("ROLLBACK", &[]).await.map(|_| ())
+    }
+
+    /// Creates a savepoint with the provided name within the current
+    /// transaction.
+    async fn create_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("SAVEPOINT {}", name), &[])
+            .await
+            .map(|_| ())
+    }
+
+    /// Releases the savepoint with the provided name, keeping its changes.
+    async fn release_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("RELEASE SAVEPOINT {}", name), &[])
+            .await
+            .map(|_| ())
+    }
+
+    /// Discards the changes made since the savepoint with the provided name
+    /// was created.
+    async fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.execute_sql_affected(&format!("ROLLBACK TO SAVEPOINT {}", name), &[])
+            .await
+            .map(|_| ())
+    }
+
+    /// Notifies the executor that the next statement it executes was compiled
+    /// from the described script. Executors may use this to attribute the
+    /// statement to its script.
+    fn on_script(&mut self, _: &ScriptInfo) {}
 }
@@ -0,0 +1,499 @@
+//! Types which wrap an executor to add behaviour around the statements it
+//! executes, like logging, timing or rewriting the SQL.
+
+#[cfg(feature = "logging")]
+mod logging;
+mod timing;
+
+#[cfg(feature = "logging")]
+pub use self::logging::LoggingLayer;
+pub use self::timing::TimingLayer;
+pub use self::timing::Timings;
+use crate::errors::ConnectionError;
+use crate::errors::RowConversionError;
+use crate::errors::SqlExecutionError;
+use crate::executors::execute_split;
+use crate::executors::CancelHandle;
+use crate::executors::ResultIter;
+use crate::executors::ResultSet;
+use crate::executors::SqlExecutor;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use crate::transactions::TransactionOptions;
+use crate::ScriptInfo;
+use core::time::Duration;
+use std::borrow::Cow;
+use std::time::Instant;
+
+/// A type which wraps executors in another executor, like the middleware of
+/// a server.
+pub trait Layer<E>
+where
+    E: SqlExecutor,
+{
+    /// The executor which wraps `E`.
+    type Executor: SqlExecutor;
+
+    /// Wraps the executor.
+    fn layer(&self, executor: E) -> Self::Executor;
+}
+
+/// Every interceptor is a layer which wraps executors in an [Intercepted]
+/// executor with a clone of the interceptor.
+impl<E, I> Layer<E> for I
+where
+    E: SqlExecutor,
+    I: Interceptor + Clone,
+{
+    type Executor = Intercepted<E, I>;
+
+    fn layer(&self, executor: E) -> Self::Executor {
+        Intercepted::new(executor, self.clone())
+    }
+}
+
+/// Hooks which are called around each statement an [Intercepted] executor
+/// executes.
+pub trait Interceptor {
+    /// Called before the statement is executed. The SQL of the statement may
+    /// be rewritten.
+    fn before(&self, _: &mut Statement<'_>) {}
+
+    /// Called once the statement has finished. A statement which returns rows
+    /// finishes once its rows have been read or dropped.
+    fn after(&self, _: &Statement<'_>, _: &Outcome<'_>) {}
+}
+
+/// A statement which is executed by an [Intercepted] executor.
+#[derive(Clone, Debug)]
+pub struct Statement<'a> {
+    script: Option<ScriptInfo>,
+    sql: Cow<'a, str>,
+    params: Cow<'a, [SqlValue]>,
+}
+
+impl<'a> Statement<'a> {
+    /// The script the SQL was compiled from, if it was compiled from one.
+    pub fn script(&self) -> Option<&ScriptInfo> {
+        self.script.as_ref()
+    }
+
+    /// The fully qualified name of the script the SQL was compiled from.
+    pub fn script_name(&self) -> Option<&'static str> {
+        self.script.as_ref().map(ScriptInfo::name)
+    }
+
+    /// The SQL which is executed.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// The parameters which are bound to the SQL.
+    pub fn params(&self) -> &[SqlValue] {
+        &self.params
+    }
+
+    /// Replaces the SQL which is executed.
+    pub fn set_sql(&mut self, sql: String) {
+        self.sql = Cow::Owned(sql);
+    }
+
+    fn into_owned(self) -> Statement<'static> {
+        Statement {
+            script: self.script,
+            sql: Cow::Owned(self.sql.into_owned()),
+            params: Cow::Owned(self.params.into_owned()),
+        }
+    }
+}
+
+/// How a statement which was executed by an [Intercepted] executor finished.
+#[derive(Clone, Copy, Debug)]
+pub struct Outcome<'a> {
+    duration: Duration,
+    rows: u64,
+    error: Option<&'a SqlExecutionError>,
+}
+
+impl<'a> Outcome<'a> {
+    /// How long the statement took, including reading its rows.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The number of rows the statement returned or affected.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// The error the statement failed with. A row which could not be read
+    /// fails the statement with the message of its error.
+    pub fn error(&self) -> Option<&'a SqlExecutionError> {
+        self.error
+    }
+}
+
+/// An executor which calls the hooks of an [Interceptor] around each statement
+/// executed by the executor it wraps.
+///
+/// Batches are passed to the wrapped executor whole, so that it may still send
+/// their statements together. The hooks of every statement of a batch run
+/// before and after the whole batch, so each statement reports the duration of
+/// the batch. Scripts with several statements run one statement at a time, so
+/// that each of them passes through the interceptor. Transactions are managed
+/// by the wrapped executor without passing through the interceptor.
+pub struct Intercepted<E, I>
+where
+    E: SqlExecutor,
+    I: Interceptor,
+{
+    executor: E,
+    interceptor: I,
+    script: Option<ScriptInfo>,
+    keep_script: bool,
+}
+
+impl<E, I> Intercepted<E, I>
+where
+    E: SqlExecutor,
+    I: Interceptor,
+{
+    /// Wraps the executor so that its statements pass through the
+    /// interceptor.
+    pub fn new(executor: E, interceptor: I) -> Self {
+        Self {
+            executor,
+            interceptor,
+            script: None,
+            keep_script: false,
+        }
+    }
+
+    /// The interceptor the statements pass through.
+    pub fn interceptor(&self) -> &I {
+        &self.interceptor
+    }
+
+    /// Consumes the executor, returning the wrapped executor.
+    pub fn into_inner(self) -> E {
+        self.executor
+    }
+
+    /// Attributes the statement to its script and calls the hook which runs
+    /// before it.
+    fn start<'s>(&mut self, sql: &'s str, params: &'s [SqlValue]) -> (Statement<'s>, Instant) {
+        let script = if self.keep_script {
+            self.script
+        } else {
+            self.script.take()
+        };
+
+        if let Some(script) = &script {
+            self.executor.on_script(script);
+        }
+
+        let mut statement = Statement {
+            script,
+            sql: Cow::Borrowed(sql),
+            params: Cow::Borrowed(params),
+        };
+
+        self.interceptor.before(&mut statement);
+        (statement, Instant::now())
+    }
+
+    /// Attributes every statement of a batch to the current script and calls
+    /// the hook which runs before each of them.
+    fn start_batch<'s>(&mut self, statements: &'s [String]) -> (Vec<Statement<'s>>, Instant) {
+        let script = self.script.take();
+        if let Some(script) = &script {
+            self.executor.on_script(script);
+        }
+
+        let interceptor = &self.interceptor;
+        let batch = statements
+            .iter()
+            .map(|sql| {
+                let mut statement = Statement {
+                    script,
+                    sql: Cow::Borrowed(sql.as_str()),
+                    params: Cow::Borrowed(&[]),
+                };
+
+                interceptor.before(&mut statement);
+                statement
+            })
+            .collect();
+
+        (batch, Instant::now())
+    }
+
+    /// Runs several statements which are all attributed to the current
+    /// script.
+    fn with_script<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.keep_script = true;
+        let result = f(self);
+        self.keep_script = false;
+        self.script = None;
+        result
+    }
+}
+
+/// The SQL of each statement of a batch, as rewritten by the interceptor.
+fn batch_sql(batch: &[Statement<'_>]) -> Vec<String> {
+    batch
+        .iter()
+        .map(|statement| statement.sql().to_owned())
+        .collect()
+}
+
+fn finish<I>(
+    interceptor: &I,
+    statement: &Statement<'_>,
+    started: Instant,
+    result: Result<u64, &SqlExecutionError>,
+) where
+    I: Interceptor,
+{
+    let outcome = Outcome {
+        duration: started.elapsed(),
+        rows: result.as_ref().map_or(0, |rows| *rows),
+        error: result.err(),
+    };
+
+    interceptor.after(statement, &outcome);
+}
+
+impl<E, I> SqlExecutor for Intercepted<E, I>
+where
+    E: SqlExecutor,
+    I: Interceptor,
+{
+    /// An interceptor cannot be created from a connection string, so this
+    /// always fails. Intercepted executors are created with
+    /// [Intercepted::new] or [Layer::layer].
+    fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Err(ConnectionError::with_source(
+            connection_string.to_owned(),
+            "An intercepted executor must be created by wrapping an executor".into(),
+        ))
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        let (statement, started) = self.start(sql, params);
+        let statement = statement.into_owned();
+        match self
+            .executor
+            .execute_sql(statement.sql(), statement.params())
+        {
+            Ok(result_set) => {
+                let (columns, rows) = result_set.into_parts();
+                let rows = InterceptedRows {
+                    rows,
+                    interceptor: &self.interceptor,
+                    statement,
+                    started,
+                    count: 0,
+                    error: None,
+                    finished: false,
+                };
+
+                Ok(ResultSet::new(columns, Box::new(rows)))
+            }
+            Err(err) => {
+                finish(&self.interceptor, &statement, started, Err(&err));
+                Err(err)
+            }
+        }
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let (statement, started) = self.start(sql, params);
+        let result = self
+            .executor
+            .execute_sql_affected(statement.sql(), statement.params());
+        finish(
+            &self.interceptor,
+            &statement,
+            started,
+            result.as_ref().copied(),
+        );
+        result
+    }
+
+    fn execute_sql_batch(&mut self, statements: &[String]) -> Vec<Result<u64, SqlExecutionError>> {
+        let (batch, started) = self.start_batch(statements);
+        let sql = batch_sql(&batch);
+        let results = self.executor.execute_sql_batch(&sql);
+        for (statement, result) in batch.iter().zip(&results) {
+            finish(
+                &self.interceptor,
+                statement,
+                started,
+                result.as_ref().copied(),
+            );
+        }
+
+        results
+    }
+
+    fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        let (batch, started) = self.start_batch(statements);
+        let sql = batch_sql(&batch);
+        let result = self.executor.execute_sql_batch_atomic(&sql);
+        match &result {
+            Ok(affected) => {
+                for (statement, rows) in batch.iter().zip(affected) {
+                    finish(&self.interceptor, statement, started, Ok(*rows));
+                }
+            }
+            Err(err) => {
+                // None of the statements take effect, so each of them fails.
+                for statement in &batch {
+                    finish(&self.interceptor, statement, started, Err(err));
+                }
+            }
+        }
+
+        result
+    }
+
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        self.with_script(|executor| execute_split(executor, sql))
+    }
+
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        let (statement, started) = self.start(sql, &[]);
+        let result = self.executor.execute_statements(statement.sql());
+        finish(
+            &self.interceptor,
+            &statement,
+            started,
+            result.as_ref().map(|_| 0),
+        );
+        result
+    }
+
+    fn on_script(&mut self, script: &ScriptInfo) {
+        self.script = Some(*script);
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SqlExecutionError> {
+        self.executor.set_timeout(timeout)
+    }
+
+    fn cancel_handle(&self) -> Option<CancelHandle> {
+        self.executor.cancel_handle()
+    }
+
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        self.executor.begin_transaction(options)
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.executor.commit_transaction()
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        self.executor.rollback_transaction()
+    }
+
+    fn create_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.executor.create_savepoint(name)
+    }
+
+    fn release_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.executor.release_savepoint(name)
+    }
+
+    fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        self.executor.rollback_to_savepoint(name)
+    }
+}
+
+/// The rows of an intercepted statement, which finish the statement once they
+/// have been read or dropped.
+struct InterceptedRows<'a, I>
+where
+    I: Interceptor,
+{
+    rows: ResultIter<'a>,
+    interceptor: &'a I,
+    statement: Statement<'static>,
+    started: Instant,
+    count: u64,
+    error: Option<SqlExecutionError>,
+    finished: bool,
+}
+
+impl<'a, I> InterceptedRows<'a, I>
+where
+    I: Interceptor,
+{
+    fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        self.finished = true;
+        let result = match &self.error {
+            Some(err) => Err(err),
+            None => Ok(self.count),
+        };
+
+        finish(self.interceptor, &self.statement, self.started, result);
+    }
+}
+
+impl<'a, I> Iterator for InterceptedRows<'a, I>
+where
+    I: Interceptor,
+{
+    type Item = Result<SqlRow, RowConversionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let row = self.rows.next();
+        match &row {
+            Some(Ok(_)) => self.count += 1,
+            Some(Err(err)) if self.error.is_none() => {
+                self.error = Some(SqlExecutionError::Other {
+                    err: err.to_string().into(),
+                });
+            }
+            Some(Err(_)) => {}
+            None => self.finish(),
+        }
+
+        row
+    }
+}
+
+impl<'a, I> Drop for InterceptedRows<'a, I>
+where
+    I: Interceptor,
+{
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
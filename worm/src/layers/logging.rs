@@ -0,0 +1,110 @@
+use crate::layers::Interceptor;
+use crate::layers::Outcome;
+use crate::layers::Statement;
+use core::time::Duration;
+use log::Level;
+
+/// A layer which logs each statement once it has finished, through the [log]
+/// crate. Failed statements are logged as errors and slow statements as
+/// warnings.
+#[derive(Clone, Debug)]
+pub struct LoggingLayer {
+    level: Level,
+    params: bool,
+    slow_threshold: Option<Duration>,
+}
+
+impl LoggingLayer {
+    /// Creates a layer which logs statements at the debug level, without
+    /// their parameters.
+    pub fn new() -> Self {
+        Self {
+            level: Level::Debug,
+            params: false,
+            slow_threshold: None,
+        }
+    }
+
+    /// Sets the level statements are logged at.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets whether the parameters of statements are logged. Parameters may
+    /// hold sensitive values, so they are not logged by default.
+    pub fn params(mut self, params: bool) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Sets how long a statement may take before it is logged as a warning.
+    pub fn slow_threshold(mut self, slow_threshold: Duration) -> Self {
+        self.slow_threshold = Some(slow_threshold);
+        self
+    }
+
+    /// Gets the level statements are logged at.
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    /// Gets whether the parameters of statements are logged.
+    pub fn get_params(&self) -> bool {
+        self.params
+    }
+
+    /// Gets how long a statement may take before it is logged as a warning.
+    pub fn get_slow_threshold(&self) -> Option<Duration> {
+        self.slow_threshold
+    }
+}
+
+impl Default for LoggingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interceptor for LoggingLayer {
+    fn after(&self, statement: &Statement<'_>, outcome: &Outcome<'_>) {
+        let slow = matches!(self.slow_threshold, Some(threshold) if outcome.duration() > threshold);
+        let level = match outcome.error() {
+            Some(_) => Level::Error,
+            None if slow => Level::Warn,
+            None => self.level,
+        };
+
+        if !log::log_enabled!(level) {
+            return;
+        }
+
+        let script = statement.script_name().unwrap_or("dynamic SQL");
+        let params = if self.params {
+            format!(" with {:?}", statement.params())
+        } else {
+            String::new()
+        };
+
+        match outcome.error() {
+            Some(err) => log::log!(
+                level,
+                "{} failed after {:?}: {}{}: {}",
+                script,
+                outcome.duration(),
+                statement.sql(),
+                params,
+                err
+            ),
+            None => log::log!(
+                level,
+                "{} took {:?} for {} rows: {}{}",
+                script,
+                outcome.duration(),
+                outcome.rows(),
+                statement.sql(),
+                params
+            ),
+        }
+    }
+}
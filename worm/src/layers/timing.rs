@@ -0,0 +1,115 @@
+use crate::layers::Interceptor;
+use crate::layers::Outcome;
+use crate::layers::Statement;
+use crate::Script;
+use crate::ScriptInfo;
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+/// How long the statements of a script took.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    count: u64,
+    errors: u64,
+    rows: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Timings {
+    /// The number of statements which were executed.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The number of statements which failed.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// The number of rows the statements returned or affected.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// How long the statements took altogether.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// How long the slowest statement took.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// How long a statement took on average.
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::default(),
+            count => Duration::from_nanos((self.total.as_nanos() / u128::from(count)) as u64),
+        }
+    }
+
+    fn record(&mut self, outcome: &Outcome<'_>) {
+        self.count += 1;
+        self.errors += outcome.error().map_or(0, |_| 1);
+        self.rows += outcome.rows();
+        self.total += outcome.duration();
+        self.max = self.max.max(outcome.duration());
+    }
+}
+
+/// A layer which times the statements of each script.
+///
+/// Clones of the layer share their timings, so a clone can be kept to read
+/// the timings of the executors the layer wrapped.
+#[derive(Clone, Default)]
+pub struct TimingLayer {
+    timings: Arc<Mutex<HashMap<Option<&'static str>, Timings>>>,
+}
+
+impl TimingLayer {
+    /// Creates a layer which has not timed any statements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The timings of each script, by the name of the script. Statements
+    /// which were not compiled from a script are timed under `None`.
+    pub fn timings(&self) -> HashMap<Option<&'static str>, Timings> {
+        self.state().clone()
+    }
+
+    /// The timings of the statements compiled from scripts of type `S`.
+    pub fn timings_of<S>(&self) -> Timings
+    where
+        S: Script,
+    {
+        let name = ScriptInfo::of::<S>().name();
+        self.state().get(&Some(name)).copied().unwrap_or_default()
+    }
+
+    /// Forgets the statements which have been timed.
+    pub fn reset(&self) {
+        self.state().clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, HashMap<Option<&'static str>, Timings>> {
+        // Timings are only added to, so a panic while holding the lock leaves
+        // them intact.
+        self.timings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Interceptor for TimingLayer {
+    fn after(&self, statement: &Statement<'_>, outcome: &Outcome<'_>) {
+        self.state()
+            .entry(statement.script_name())
+            .or_default()
+            .record(outcome);
+    }
+}
@@ -6,11 +6,20 @@
 pub mod connections;
 pub mod errors;
 pub mod executors;
+pub mod layers;
 pub mod pooling;
 pub mod results;
 pub mod sql;
+pub mod testing;
+pub mod transactions;
 
 use self::sql::SqlResult;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use core::fmt::Result as FmtResult;
+use core::time::Duration;
+#[cfg(feature = "testing")]
+pub use worm_macros::test;
 pub use worm_macros::Script;
 
 /// A type that represents a templated script.
@@ -18,6 +27,87 @@ pub trait Script {
     /// The Rust type that rows returned by the script should be interpretted as.
     type Output: SqlResult;
 
+    /// The number of rows the script is expected to return.
+    const RETURNS: Returns = Returns::Many;
+
+    /// The longest the script may run before the database cancels it. This
+    /// takes precedence over the timeout of the connection.
+    const TIMEOUT: Option<Duration> = None;
+
     /// Compiles the instance of the type into its SQL form.
     fn compile(self) -> String;
 }
+
+/// Describes the [Script] type that a statement was compiled from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ScriptInfo {
+    name: &'static str,
+    returns: Returns,
+    timeout: Option<Duration>,
+}
+
+impl ScriptInfo {
+    /// Creates the description of the script type `S`.
+    pub fn of<S>() -> Self
+    where
+        S: Script,
+    {
+        Self {
+            name: core::any::type_name::<S>(),
+            returns: S::RETURNS,
+            timeout: S::TIMEOUT,
+        }
+    }
+
+    /// The fully qualified name of the script type.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The number of rows the script is expected to return.
+    pub fn returns(&self) -> Returns {
+        self.returns
+    }
+
+    /// The longest the script may run before the database cancels it.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// The number of rows a [Script] is expected to return.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Returns {
+    /// The script returns no rows and is only run for its side effects.
+    None,
+    /// The script returns exactly one row.
+    One,
+    /// The script returns at most one row.
+    Optional,
+    /// The script returns any number of rows.
+    Many,
+}
+
+impl Returns {
+    /// Checks if a script declared with this cardinality can be fetched with
+    /// the `requested` cardinality.
+    pub fn allows(self, requested: Returns) -> bool {
+        match (self, requested) {
+            (Returns::Many, _) => true,
+            (declared, Returns::Many) => declared != Returns::None,
+            (Returns::One, Returns::Optional) => true,
+            (declared, requested) => declared == requested,
+        }
+    }
+}
+
+impl Display for Returns {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Returns::None => f.write_str("none"),
+            Returns::One => f.write_str("one"),
+            Returns::Optional => f.write_str("optional"),
+            Returns::Many => f.write_str("many"),
+        }
+    }
+}
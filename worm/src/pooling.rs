@@ -3,6 +3,7 @@
 #![cfg(feature = "pooling")]
 
 use crate::connections::Connection;
+use crate::errors::ConnectionError;
 use crate::errors::WormError;
 use crate::executors::SqlExecutor;
 use crate::Script;
@@ -24,7 +25,7 @@ pub struct ConnectionManager<E>
 where
     E: SqlExecutor + Send,
 {
-    connection_string: String,
+    connect: Box<dyn Fn() -> Result<E, ConnectionError> + Send + Sync>,
     __: PhantomData<E>,
 }
 
@@ -34,8 +35,18 @@ where
 {
     /// Constructs a new connection manager using the provided connection string.
     pub fn new(connection_string: String) -> Self {
+        Self::with_connect(move || E::connect(&connection_string))
+    }
+
+    /// Constructs a new connection manager which creates executors with the
+    /// provided function, like an executor's constructor which takes
+    /// structured options instead of a connection string.
+    pub fn with_connect<F>(connect: F) -> Self
+    where
+        F: Fn() -> Result<E, ConnectionError> + Send + Sync + 'static,
+    {
         Self {
-            connection_string,
+            connect: Box::new(connect),
             __: PhantomData {},
         }
     }
@@ -52,7 +63,7 @@ where
     type Error = WormError;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        let executor = E::connect(&self.connection_string)?;
+        let executor = (self.connect)()?;
         Ok(Connection::new(executor))
     }
 
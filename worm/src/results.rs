@@ -1,9 +1,34 @@
 //! Types that represent the results of executed queries.
 
+use crate::connections::Connection;
+use crate::errors::RawRowConversionError;
 use crate::errors::RowConversionError;
-use crate::executors::ResultIter;
+use crate::errors::SqlExecutionError;
+use crate::executors::ResultSet;
+#[cfg(feature = "async")]
+use crate::executors::ResultStream;
+use crate::executors::SqlExecutor;
+use crate::sql::ColumnDescription;
 use crate::sql::SqlResult;
+use crate::sql::SqlResultSets;
+use crate::sql::SqlRow;
+use crate::transactions::Transaction;
+use crate::transactions::TransactionOptions;
+use crate::ScriptInfo;
 use core::marker::PhantomData;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+#[cfg(feature = "async")]
+use core::task::Context;
+#[cfg(feature = "async")]
+use core::task::Poll;
+#[cfg(feature = "async")]
+use futures_core::Stream;
+use std::collections::VecDeque;
+
+/// The name of the cursor used by [CursorResults]. The results borrow their
+/// connection, so only one cursor is ever open on a connection at a time.
+const CURSOR_NAME: &str = "worm_cursor";
 
 /// The results of a query.
 pub struct QueryResults<'a, T>
@@ -11,19 +36,26 @@ where
     T: SqlResult,
 {
     __: PhantomData<T>,
-    row_iter: ResultIter<'a>,
+    row_iter: ResultSet<'a>,
 }
 
 impl<'a, T> QueryResults<'a, T>
 where
     T: SqlResult,
 {
-    pub(crate) fn new(row_iter: ResultIter<'a>) -> Self {
+    /// Creates the results of a query from the rows returned by an executor.
+    pub fn new(row_iter: impl Into<ResultSet<'a>>) -> Self {
         Self {
             __: PhantomData {},
-            row_iter,
+            row_iter: row_iter.into(),
         }
     }
+
+    /// The descriptions of the columns of the query, which are empty if the
+    /// executor does not report them.
+    pub fn columns(&self) -> &[ColumnDescription] {
+        self.row_iter.columns()
+    }
 }
 
 impl<'a, T> Iterator for QueryResults<'a, T>
@@ -33,10 +65,195 @@ where
     type Item = Result<T, RowConversionError>;
     fn next(&mut self) -> Option<Self::Item> {
         let row_result = self.row_iter.next()?;
-        let struct_result = row_result
-            .and_then(T::from_row)
-            .map_err(RowConversionError::from);
+        let struct_result = row_result.and_then(T::from_row);
 
         Some(struct_result)
     }
 }
+
+/// The result sets of a script with several statements, in the order of the
+/// statements which returned them. Each set is read as the type at its
+/// position in the tuple `T`.
+pub struct MultiQueryResults<T>
+where
+    T: SqlResultSets,
+{
+    __: PhantomData<T>,
+    sets: Vec<ResultSet<'static>>,
+}
+
+impl<T> MultiQueryResults<T>
+where
+    T: SqlResultSets,
+{
+    pub(crate) fn new(sets: Vec<ResultSet<'static>>) -> Self {
+        Self {
+            __: PhantomData {},
+            sets,
+        }
+    }
+
+    /// The descriptions of the columns of each result set.
+    pub fn columns(&self) -> Vec<&[ColumnDescription]> {
+        self.sets.iter().map(ResultSet::columns).collect()
+    }
+
+    /// Consumes the results, returning the results of each set.
+    pub fn into_sets(self) -> T::Results {
+        T::from_sets(self.sets)
+    }
+
+    /// Consumes the results, collecting the records of each set.
+    pub fn collect_sets(self) -> Result<T::Records, RowConversionError> {
+        T::collect(self.into_sets())
+    }
+}
+
+/// The results of a query which are fetched from a server-side cursor in
+/// chunks, so that only one chunk of rows is held in memory at a time.
+///
+/// The cursor lives in a transaction, or a savepoint if the connection is
+/// already within one. Dropping the results closes the cursor and commits.
+pub struct CursorResults<'a, E, T>
+where
+    E: SqlExecutor,
+    T: SqlResult,
+{
+    __: PhantomData<T>,
+    transaction: Option<Transaction<'a, E>>,
+    fetch_sql: String,
+    fetch_size: usize,
+    rows: VecDeque<Result<SqlRow, RowConversionError>>,
+    exhausted: bool,
+}
+
+impl<'a, E, T> CursorResults<'a, E, T>
+where
+    E: SqlExecutor,
+    T: SqlResult,
+{
+    pub(crate) fn declare(
+        connection: &'a mut Connection<E>,
+        script: &ScriptInfo,
+        sql: &str,
+        fetch_size: usize,
+    ) -> Result<Self, SqlExecutionError> {
+        // A terminated statement cannot be the query of a cursor.
+        let query = sql.trim_end_matches(|c: char| c == ';' || c.is_whitespace());
+        let mut transaction = Transaction::begin(connection, &TransactionOptions::default())?;
+        transaction.start_script(script)?;
+        transaction.executor_mut().execute_statements(&format!(
+            "DECLARE {} NO SCROLL CURSOR FOR {}",
+            CURSOR_NAME, query
+        ))?;
+
+        let fetch_size = fetch_size.max(1);
+        Ok(Self {
+            __: PhantomData {},
+            transaction: Some(transaction),
+            fetch_sql: format!("FETCH FORWARD {} FROM {}", fetch_size, CURSOR_NAME),
+            fetch_size,
+            rows: VecDeque::with_capacity(fetch_size),
+            exhausted: false,
+        })
+    }
+
+    /// Fetches the next chunk of rows from the cursor.
+    fn fetch(&mut self) -> Result<(), SqlExecutionError> {
+        let transaction = match &mut self.transaction {
+            Some(transaction) => transaction,
+            None => return Ok(()),
+        };
+
+        // Every cursor shares its name, so the same fetch returns columns of
+        // other types for each cursor and must not be cached by the executor.
+        let sets = transaction
+            .executor_mut()
+            .execute_sql_multi(&self.fetch_sql)?;
+        for set in sets {
+            self.rows.extend(set);
+        }
+        self.exhausted = self.rows.len() < self.fetch_size;
+        Ok(())
+    }
+}
+
+impl<'a, E, T> Iterator for CursorResults<'a, E, T>
+where
+    E: SqlExecutor,
+    T: SqlResult,
+{
+    type Item = Result<T, RowConversionError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rows.is_empty() && !self.exhausted {
+            if let Err(err) = self.fetch() {
+                self.exhausted = true;
+                let err = RawRowConversionError::Other { err: Box::new(err) };
+                return Some(Err(err.into()));
+            }
+        }
+
+        let row_result = self.rows.pop_front()?;
+        Some(row_result.and_then(T::from_row))
+    }
+}
+
+impl<'a, E, T> Drop for CursorResults<'a, E, T>
+where
+    E: SqlExecutor,
+    T: SqlResult,
+{
+    fn drop(&mut self) {
+        if let Some(mut transaction) = self.transaction.take() {
+            // There is no way to report the errors while dropping. If the
+            // cursor cannot be closed, the transaction has failed and rolling
+            // it back discards the cursor.
+            let closed = transaction
+                .executor_mut()
+                .execute_statements(&format!("CLOSE {}", CURSOR_NAME));
+            let _ = match closed {
+                Ok(()) => transaction.commit(),
+                Err(_) => transaction.rollback(),
+            };
+        }
+    }
+}
+
+/// The results of a query which are received asynchronously.
+#[cfg(feature = "async")]
+pub struct AsyncQueryResults<'a, T>
+where
+    T: SqlResult,
+{
+    __: PhantomData<fn() -> T>,
+    row_stream: ResultStream<'a>,
+}
+
+#[cfg(feature = "async")]
+impl<'a, T> AsyncQueryResults<'a, T>
+where
+    T: SqlResult,
+{
+    pub(crate) fn new(row_stream: ResultStream<'a>) -> Self {
+        Self {
+            __: PhantomData {},
+            row_stream,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, T> Stream for AsyncQueryResults<'a, T>
+where
+    T: SqlResult,
+{
+    type Item = Result<T, RowConversionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .row_stream
+            .as_mut()
+            .poll_next(cx)
+            .map(|row_result| row_result.map(|row_result| row_result.and_then(T::from_row)))
+    }
+}
@@ -1,23 +1,79 @@
 //! Types that represent raw SQL.
 
+mod columns;
+#[cfg(feature = "serde")]
+mod de;
 mod fields;
 mod rows;
+mod statements;
 mod values;
 
+pub use self::columns::ColumnDescription;
+#[cfg(feature = "serde")]
+pub use self::de::from_row;
+#[cfg(feature = "serde")]
+pub use self::de::Serde;
+#[cfg(feature = "serde")]
+pub use self::de::SqlRowDeserializer;
+#[cfg(feature = "serde")]
+pub use self::de::SqlValueDeserializer;
 pub use self::fields::RecordField;
+pub use self::rows::RowIndex;
 pub use self::rows::SqlRow;
+pub use self::statements::split_statements;
 pub use self::values::SqlValue;
+pub use self::values::SqlValueKind;
 use crate::errors::RowConversionError;
+use crate::executors::ResultSet;
+use crate::results::QueryResults;
+pub use worm_macros::SqlRecord;
 pub use worm_macros::SqlResult;
 
 /// A type that can be returned by a SQL query.
 pub trait SqlResult {
+    /// The number of result sets the type is read from. Only a tuple of
+    /// several types, which must be executed with
+    /// [Connection::execute_multi](crate::connections::Connection::execute_multi),
+    /// is read from more than one.
+    const RESULT_SETS: usize = 1;
+
     /// Converts a SQL row into an instance of the type.
     fn from_row(row: SqlRow) -> Result<Self, RowConversionError>
     where
         Self: Sized;
 }
 
+/// A tuple of types which are each returned by one result set of a script
+/// with several statements, in the order of the statements.
+pub trait SqlResultSets {
+    /// The number of result sets the script returns.
+    const COUNT: usize;
+
+    /// The results of each set.
+    type Results;
+
+    /// The records of each set.
+    type Records;
+
+    /// Reads each result set as the type at its position. There must be
+    /// [SqlResultSets::COUNT] sets.
+    fn from_sets(sets: Vec<ResultSet<'static>>) -> Self::Results;
+
+    /// Collects the records of each set.
+    fn collect(results: Self::Results) -> Result<Self::Records, RowConversionError>;
+}
+
+/// A type whose fields can be written to the columns of a table, like the rows
+/// loaded by a bulk copy.
+pub trait SqlRecord {
+    /// The names of the columns the fields are written to.
+    fn columns() -> &'static [&'static str];
+
+    /// Converts the record into the values of its fields, in the order of
+    /// [SqlRecord::columns].
+    fn into_values(self) -> Vec<SqlValue>;
+}
+
 impl SqlResult for () {
     fn from_row(_: SqlRow) -> Result<Self, RowConversionError>
     where
@@ -26,3 +82,67 @@ impl SqlResult for () {
         Ok(())
     }
 }
+
+impl SqlResult for SqlRow {
+    fn from_row(row: SqlRow) -> Result<Self, RowConversionError>
+    where
+        Self: Sized,
+    {
+        Ok(row)
+    }
+}
+
+/// Implements [SqlResult] and [SqlResultSets] for a tuple of result types.
+/// The [SqlResult] implementation only declares the number of result sets the
+/// tuple is read from, so that executing a script whose output is a tuple of
+/// several types as a single result set is rejected.
+macro_rules! impl_tuple_results {
+    ($count:expr; $($name:ident),+) => {
+        impl<$($name),+> SqlResult for ($($name,)+)
+        where
+            $($name: SqlResult,)+
+        {
+            const RESULT_SETS: usize = $count;
+
+            fn from_row(row: SqlRow) -> Result<Self, RowConversionError>
+            where
+                Self: Sized,
+            {
+                Ok(($($name::from_row(row.clone())?,)+))
+            }
+        }
+
+        impl<$($name),+> SqlResultSets for ($($name,)+)
+        where
+            $($name: SqlResult,)+
+        {
+            const COUNT: usize = $count;
+
+            type Results = ($(QueryResults<'static, $name>,)+);
+
+            type Records = ($(Vec<$name>,)+);
+
+            fn from_sets(sets: Vec<ResultSet<'static>>) -> Self::Results {
+                let mut sets = sets.into_iter();
+                ($(QueryResults::<$name>::new(
+                    sets.next().expect("a result set for each type of the tuple"),
+                ),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn collect(results: Self::Results) -> Result<Self::Records, RowConversionError> {
+                let ($($name,)+) = results;
+                Ok(($($name.collect::<Result<Vec<_>, _>>()?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple_results!(1; A);
+impl_tuple_results!(2; A, B);
+impl_tuple_results!(3; A, B, C);
+impl_tuple_results!(4; A, B, C, D);
+impl_tuple_results!(5; A, B, C, D, E);
+impl_tuple_results!(6; A, B, C, D, E, F);
+impl_tuple_results!(7; A, B, C, D, E, F, G);
+impl_tuple_results!(8; A, B, C, D, E, F, G, H);
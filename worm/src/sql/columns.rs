@@ -0,0 +1,97 @@
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// The description of a column of a result set, as reported by the executor.
+/// Anything other than the name is only set if the DBMS reports it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ColumnDescription {
+    name: String,
+    type_name: Option<String>,
+    type_id: Option<u32>,
+    nullable: Option<bool>,
+    table: Option<String>,
+    table_id: Option<u32>,
+}
+
+impl ColumnDescription {
+    /// Creates the description of a column which only names it.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            type_name: None,
+            type_id: None,
+            nullable: None,
+            table: None,
+            table_id: None,
+        }
+    }
+
+    /// Sets the name the DBMS gives the type of the column.
+    pub fn type_name(mut self, type_name: &str) -> Self {
+        self.type_name = Some(type_name.to_owned());
+        self
+    }
+
+    /// Sets the identifier the DBMS gives the type of the column, like the OID
+    /// of a postgres type.
+    pub fn type_id(mut self, type_id: u32) -> Self {
+        self.type_id = Some(type_id);
+        self
+    }
+
+    /// Sets whether the column may hold nulls.
+    pub fn nullable(mut self, nullable: bool) -> Self {
+        self.nullable = Some(nullable);
+        self
+    }
+
+    /// Sets the name of the table the column was read from. Postgres only
+    /// reports the OID of the table, so its executor sets
+    /// [ColumnDescription::table_id] instead.
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = Some(table.to_owned());
+        self
+    }
+
+    /// Sets the identifier the DBMS gives the table the column was read from,
+    /// like the OID of a postgres table.
+    pub fn table_id(mut self, table_id: u32) -> Self {
+        self.table_id = Some(table_id);
+        self
+    }
+
+    /// Gets the name of the column.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the name the DBMS gives the type of the column.
+    pub fn get_type_name(&self) -> Option<&str> {
+        self.type_name.as_deref()
+    }
+
+    /// Gets the identifier the DBMS gives the type of the column.
+    pub fn get_type_id(&self) -> Option<u32> {
+        self.type_id
+    }
+
+    /// Gets whether the column may hold nulls.
+    pub fn get_nullable(&self) -> Option<bool> {
+        self.nullable
+    }
+
+    /// Gets the name of the table the column was read from. This is always
+    /// `None` for postgres, which only reports the OID of the table through
+    /// [ColumnDescription::get_table_id].
+    pub fn get_table(&self) -> Option<&str> {
+        self.table.as_deref()
+    }
+
+    /// Gets the identifier the DBMS gives the table the column was read from.
+    pub fn get_table_id(&self) -> Option<u32> {
+        self.table_id
+    }
+}
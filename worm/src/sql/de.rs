@@ -0,0 +1,484 @@
+use crate::errors::DeserializationError;
+use crate::errors::RowConversionError;
+use crate::sql::SqlResult;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use serde::de::value::StringDeserializer;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::Deserializer;
+use serde::de::IntoDeserializer;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::forward_to_deserialize_any;
+use std::vec::IntoIter;
+
+/// An output adapter which converts rows into any type implementing
+/// [serde::Deserialize]. Rows are deserialized as maps keyed by column name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Serde<T>(pub T);
+
+impl<T> Serde<T> {
+    /// Unwraps the deserialized value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> SqlResult for Serde<T>
+where
+    T: DeserializeOwned,
+{
+    fn from_row(row: SqlRow) -> Result<Self, RowConversionError>
+    where
+        Self: Sized,
+    {
+        let value = from_row(row)?;
+        Ok(Serde(value))
+    }
+}
+
+/// Deserializes a [SqlRow] into any type implementing [serde::Deserialize].
+pub fn from_row<T>(row: SqlRow) -> Result<T, DeserializationError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(SqlRowDeserializer::new(row))
+}
+
+/// A [Deserializer] which reads a [SqlRow] as a map of column names to values,
+/// or as a sequence of values. Structs are matched to rows without column
+/// names by the order of their fields, and maps are keyed by the position of
+/// each value instead.
+pub struct SqlRowDeserializer {
+    row: SqlRow,
+}
+
+impl SqlRowDeserializer {
+    /// Creates a new deserializer for the provided row.
+    pub fn new(row: SqlRow) -> Self {
+        Self { row }
+    }
+
+    fn into_map(self, fields: Option<&'static [&'static str]>) -> RowMapAccess {
+        // Rows without column names are matched against the fields of a
+        // struct in order, or keyed by position.
+        let columns = if self.row.columns().is_empty() {
+            match fields {
+                Some(fields) => fields.iter().map(|field| field.to_string()).collect(),
+                None => (0..self.row.len()).map(|i| i.to_string()).collect(),
+            }
+        } else {
+            self.row.columns().to_vec()
+        };
+
+        RowMapAccess {
+            columns: columns.into_iter(),
+            values: self.row.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for SqlRowDeserializer {
+    type Error = DeserializationError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.row.columns().is_empty() {
+            self.deserialize_seq(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(RowSeqAccess {
+            values: self.row.into_iter(),
+        })
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(self.into_map(None))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(self.into_map(Some(fields)))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct enum identifier ignored_any
+    }
+}
+
+struct RowMapAccess {
+    columns: IntoIter<String>,
+    values: IntoIter<SqlValue>,
+    value: Option<SqlValue>,
+}
+
+impl<'de> MapAccess<'de> for RowMapAccess {
+    type Error = DeserializationError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match (self.columns.next(), self.values.next()) {
+            (Some(column), Some(value)) => {
+                self.value = Some(value);
+                let key: StringDeserializer<Self::Error> = column.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self.value.take().unwrap_or(SqlValue::Null);
+        seed.deserialize(SqlValueDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len().min(self.columns.len()))
+    }
+}
+
+struct RowSeqAccess {
+    values: IntoIter<SqlValue>,
+}
+
+impl<'de> SeqAccess<'de> for RowSeqAccess {
+    type Error = DeserializationError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.values.next() {
+            Some(value) => seed.deserialize(SqlValueDeserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+/// A [Deserializer] which reads a single [SqlValue].
+pub struct SqlValueDeserializer {
+    value: SqlValue,
+}
+
+impl SqlValueDeserializer {
+    /// Creates a new deserializer for the provided value.
+    pub fn new(value: SqlValue) -> Self {
+        Self { value }
+    }
+}
+
+impl<'de> Deserializer<'de> for SqlValueDeserializer {
+    type Error = DeserializationError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            #[cfg(feature = "sql-value-bool")]
+            SqlValue::Boolean(b) => visitor.visit_bool(b),
+            #[cfg(feature = "sql-value-f32")]
+            SqlValue::Float32(float) => visitor.visit_f32(float),
+            #[cfg(feature = "sql-value-f64")]
+            SqlValue::Float64(float) => visitor.visit_f64(float),
+            SqlValue::Null => visitor.visit_unit(),
+            #[cfg(feature = "sql-value-string")]
+            SqlValue::String(string) => visitor.visit_string(string),
+            #[cfg(feature = "sql-value-i8")]
+            SqlValue::Signed8(integer) => visitor.visit_i8(integer),
+            #[cfg(feature = "sql-value-i16")]
+            SqlValue::Signed16(integer) => visitor.visit_i16(integer),
+            #[cfg(feature = "sql-value-i32")]
+            SqlValue::Signed32(integer) => visitor.visit_i32(integer),
+            #[cfg(feature = "sql-value-i64")]
+            SqlValue::Signed64(integer) => visitor.visit_i64(integer),
+            #[cfg(feature = "sql-value-i128")]
+            SqlValue::Signed128(integer) => visitor.visit_i128(integer),
+            #[cfg(feature = "sql-value-u8")]
+            SqlValue::Unsigned8(integer) => visitor.visit_u8(integer),
+            #[cfg(feature = "sql-value-u16")]
+            SqlValue::Unsigned16(integer) => visitor.visit_u16(integer),
+            #[cfg(feature = "sql-value-u32")]
+            SqlValue::Unsigned32(integer) => visitor.visit_u32(integer),
+            #[cfg(feature = "sql-value-u64")]
+            SqlValue::Unsigned64(integer) => visitor.visit_u64(integer),
+            #[cfg(feature = "sql-value-u128")]
+            SqlValue::Unsigned128(integer) => visitor.visit_u128(integer),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if matches!(self.value, SqlValue::Null) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[cfg_attr(not(feature = "sql-value-string"), allow(unused_variables))]
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        // Enums are stored as the name of one of their unit variants.
+        match self.value {
+            #[cfg(feature = "sql-value-string")]
+            SqlValue::String(string) => {
+                let variant: StringDeserializer<Self::Error> = string.into_deserializer();
+                variant.deserialize_enum(name, variants, visitor)
+            }
+            other => Err(DeserializationError::new(format!(
+                "The value {} cannot be converted into the enum '{}'",
+                other, name
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "sql-value-i64", feature = "sql-value-string"))]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn row(columns: &[&str], values: Vec<SqlValue>) -> SqlRow {
+        let columns = columns
+            .iter()
+            .map(|column| column.to_string())
+            .collect::<Vec<_>>();
+        SqlRow::new(Arc::from(columns), values)
+    }
+
+    fn text(text: &str) -> SqlValue {
+        SqlValue::String(String::from(text))
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn reads_structs_by_column_name_or_position() {
+        let named = row(&["name", "id"], vec![text("ada"), SqlValue::Signed64(1)]);
+        let positional = row(&[], vec![SqlValue::Signed64(1), text("ada")]);
+        let expected = || User {
+            id: 1,
+            name: String::from("ada"),
+        };
+
+        assert_eq!(from_row(named), Ok(expected()));
+        assert_eq!(from_row(positional), Ok(expected()));
+    }
+
+    #[test]
+    fn reads_maps_keyed_by_column_name_or_position() {
+        let named = row(&["first", "last"], vec![text("grace"), text("hopper")]);
+        let positional = row(&[], vec![text("grace"), text("hopper")]);
+
+        let by_name = from_row::<BTreeMap<String, String>>(named).unwrap();
+        assert_eq!(
+            by_name.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("first"), String::from("grace")),
+                (String::from("last"), String::from("hopper")),
+            ]
+        );
+
+        let by_position = from_row::<BTreeMap<String, String>>(positional).unwrap();
+        assert_eq!(
+            by_position.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("0"), String::from("grace")),
+                (String::from("1"), String::from("hopper")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_positional_rows_as_sequences() {
+        let positional = row(&[], vec![SqlValue::Signed64(1), text("ada")]);
+        assert_eq!(
+            from_row::<(i64, String)>(positional),
+            Ok((1, String::from("ada")))
+        );
+
+        let named = row(&["id"], vec![SqlValue::Signed64(1)]);
+        assert_eq!(from_row::<Vec<i64>>(named), Ok(vec![1]));
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Nullable {
+        nickname: Option<Option<String>>,
+    }
+
+    #[test]
+    fn reads_nested_options() {
+        let null = row(&["nickname"], vec![SqlValue::Null]);
+        let set = row(&["nickname"], vec![text("ada")]);
+        let missing = row(&["id"], vec![SqlValue::Signed64(1)]);
+
+        assert_eq!(from_row(null), Ok(Nullable { nickname: None }));
+        assert_eq!(
+            from_row(set),
+            Ok(Nullable {
+                nickname: Some(Some(String::from("ada")))
+            })
+        );
+        assert_eq!(from_row(missing), Ok(Nullable { nickname: None }));
+
+        // A row is always present, so an optional row holds it.
+        let user = row(&["id", "name"], vec![SqlValue::Signed64(1), text("ada")]);
+        assert!(from_row::<Option<User>>(user).unwrap().is_some());
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Role {
+        Admin,
+        Member,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Membership {
+        role: Role,
+    }
+
+    #[test]
+    fn reads_enums_from_the_names_of_their_variants() {
+        let admin = row(&["role"], vec![text("admin")]);
+        let member = row(&["role"], vec![text("member")]);
+        assert_eq!(from_row(admin), Ok(Membership { role: Role::Admin }));
+        assert_eq!(from_row(member), Ok(Membership { role: Role::Member }));
+
+        let unknown = row(&["role"], vec![text("owner")]);
+        assert!(from_row::<Membership>(unknown).is_err());
+
+        let integer = row(&["role"], vec![SqlValue::Signed64(1)]);
+        let err = from_row::<Membership>(integer).unwrap_err();
+        assert!(err.to_string().contains("'Role'"), "{}", err);
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct UserId(i64);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Account {
+        id: UserId,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Wrapped(User);
+
+    #[test]
+    fn reads_newtypes_of_values_and_rows() {
+        let account = row(&["id"], vec![SqlValue::Signed64(7)]);
+        assert_eq!(from_row(account), Ok(Account { id: UserId(7) }));
+
+        let user = row(&["id", "name"], vec![SqlValue::Signed64(1), text("ada")]);
+        assert_eq!(
+            from_row(user),
+            Ok(Wrapped(User {
+                id: 1,
+                name: String::from("ada"),
+            }))
+        );
+    }
+}
@@ -12,6 +12,51 @@ pub trait RecordField {
     fn into_sql(self) -> SqlValue;
 }
 
+/// Converts an integer of any width into the integer type of a field, since
+/// databases such as SQLite return every integer with the same width.
+#[cfg(any(
+    feature = "sql-value-i8",
+    feature = "sql-value-i16",
+    feature = "sql-value-i32",
+    feature = "sql-value-i64",
+    feature = "sql-value-i128",
+    feature = "sql-value-u8",
+    feature = "sql-value-u16",
+    feature = "sql-value-u32",
+    feature = "sql-value-u64",
+    feature = "sql-value-u128"
+))]
+fn integer_from_sql<T>(sql_value: SqlValue) -> Result<T, FieldConversionError>
+where
+    T: core::convert::TryFrom<i128> + core::convert::TryFrom<u128>,
+{
+    let converted = match sql_value {
+        #[cfg(feature = "sql-value-i8")]
+        SqlValue::Signed8(i) => T::try_from(i128::from(i)).ok(),
+        #[cfg(feature = "sql-value-i16")]
+        SqlValue::Signed16(i) => T::try_from(i128::from(i)).ok(),
+        #[cfg(feature = "sql-value-i32")]
+        SqlValue::Signed32(i) => T::try_from(i128::from(i)).ok(),
+        #[cfg(feature = "sql-value-i64")]
+        SqlValue::Signed64(i) => T::try_from(i128::from(i)).ok(),
+        #[cfg(feature = "sql-value-i128")]
+        SqlValue::Signed128(i) => T::try_from(i).ok(),
+        #[cfg(feature = "sql-value-u8")]
+        SqlValue::Unsigned8(u) => T::try_from(u128::from(u)).ok(),
+        #[cfg(feature = "sql-value-u16")]
+        SqlValue::Unsigned16(u) => T::try_from(u128::from(u)).ok(),
+        #[cfg(feature = "sql-value-u32")]
+        SqlValue::Unsigned32(u) => T::try_from(u128::from(u)).ok(),
+        #[cfg(feature = "sql-value-u64")]
+        SqlValue::Unsigned64(u) => T::try_from(u128::from(u)).ok(),
+        #[cfg(feature = "sql-value-u128")]
+        SqlValue::Unsigned128(u) => T::try_from(u).ok(),
+        _ => return Err(FieldConversionError::IncorrectType),
+    };
+
+    converted.ok_or(FieldConversionError::ValueOutOfBounds)
+}
+
 #[cfg(feature = "sql-value-bool")]
 impl RecordField for bool {
     fn from_sql(sql_value: SqlValue) -> Result<Self, FieldConversionError>
@@ -90,11 +135,7 @@ impl RecordField for i8 {
     where
         Self: Sized,
     {
-        if let SqlValue::Signed8(i) = sql_value {
-            Ok(i)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -108,11 +149,7 @@ impl RecordField for i16 {
     where
         Self: Sized,
     {
-        if let SqlValue::Signed16(i) = sql_value {
-            Ok(i)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -126,11 +163,7 @@ impl RecordField for i32 {
     where
         Self: Sized,
     {
-        if let SqlValue::Signed32(i) = sql_value {
-            Ok(i)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -144,11 +177,7 @@ impl RecordField for i64 {
     where
         Self: Sized,
     {
-        if let SqlValue::Signed64(i) = sql_value {
-            Ok(i)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -162,11 +191,7 @@ impl RecordField for i128 {
     where
         Self: Sized,
     {
-        if let SqlValue::Signed128(i) = sql_value {
-            Ok(i)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -180,11 +205,7 @@ impl RecordField for u8 {
     where
         Self: Sized,
     {
-        if let SqlValue::Unsigned8(u) = sql_value {
-            Ok(u)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -198,11 +219,7 @@ impl RecordField for u16 {
     where
         Self: Sized,
     {
-        if let SqlValue::Unsigned16(u) = sql_value {
-            Ok(u)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -216,11 +233,7 @@ impl RecordField for u32 {
     where
         Self: Sized,
     {
-        if let SqlValue::Unsigned32(u) = sql_value {
-            Ok(u)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -234,11 +247,7 @@ impl RecordField for u64 {
     where
         Self: Sized,
     {
-        if let SqlValue::Unsigned64(u) = sql_value {
-            Ok(u)
-        } else {
-            Err(FieldConversionError::IncorrectType)
-        }
+        integer_from_sql(sql_value)
     }
 
     fn into_sql(self) -> SqlValue {
@@ -252,14 +261,69 @@ impl RecordField for u128 {
     where
         Self: Sized,
     {
-        if let SqlValue::Unsigned128(u) = sql_value {
-            Ok(u)
+        integer_from_sql(sql_value)
+    }
+
+    fn into_sql(self) -> SqlValue {
+        SqlValue::Unsigned128(self)
+    }
+}
+
+impl<T> RecordField for Option<T>
+where
+    T: RecordField,
+{
+    fn from_sql(sql_value: SqlValue) -> Result<Self, FieldConversionError>
+    where
+        Self: Sized,
+    {
+        if sql_value == SqlValue::Null {
+            Ok(None)
         } else {
-            Err(FieldConversionError::IncorrectType)
+            T::from_sql(sql_value).map(Some)
         }
     }
 
     fn into_sql(self) -> SqlValue {
-        SqlValue::Unsigned128(self)
+        self.map(T::into_sql).unwrap_or(SqlValue::Null)
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(
+    feature = "sql-value-i32",
+    feature = "sql-value-i64",
+    feature = "sql-value-u8"
+))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_integers_of_other_widths_which_fit() {
+        assert_eq!(i32::from_sql(SqlValue::Signed64(-42)), Ok(-42));
+        assert_eq!(u8::from_sql(SqlValue::Signed64(255)), Ok(255));
+        assert_eq!(i64::from_sql(SqlValue::Unsigned8(7)), Ok(7));
+        assert_eq!(Option::<i32>::from_sql(SqlValue::Signed64(1)), Ok(Some(1)));
+    }
+
+    #[test]
+    fn rejects_integers_which_do_not_fit() {
+        assert_eq!(
+            i32::from_sql(SqlValue::Signed64(i64::from(i32::MAX) + 1)),
+            Err(FieldConversionError::ValueOutOfBounds)
+        );
+        assert_eq!(
+            u8::from_sql(SqlValue::Signed32(-1)),
+            Err(FieldConversionError::ValueOutOfBounds)
+        );
+    }
+
+    #[cfg(feature = "sql-value-string")]
+    #[test]
+    fn rejects_values_which_are_not_integers() {
+        assert_eq!(
+            i32::from_sql(SqlValue::String(String::from("1"))),
+            Err(FieldConversionError::IncorrectType)
+        );
     }
 }
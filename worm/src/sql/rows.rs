@@ -1,12 +1,110 @@
+use crate::errors::RowConversionError;
+use crate::sql::RecordField;
 use crate::sql::SqlValue;
+use core::fmt::Display;
 use core::iter::FromIterator;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::sync::Arc;
 
 /// A raw SQL row.
-pub struct SqlRow(Vec<SqlValue>);
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SqlRow {
+    /// The names of the columns of the row. This is empty if the executor did
+    /// not report any column names.
+    columns: Arc<[String]>,
+    /// The values of the row in column order.
+    values: Vec<SqlValue>,
+}
+
+impl SqlRow {
+    /// Creates a new row from a set of column names and the corresponding
+    /// values. The column names are shared so that every row of a result set
+    /// can reference the same allocation.
+    pub fn new(columns: Arc<[String]>, values: Vec<SqlValue>) -> Self {
+        Self { columns, values }
+    }
+
+    /// The number of values in the row.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks if the row contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The names of the columns of the row.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Gets the value at the provided position.
+    pub fn get(&self, index: usize) -> Option<&SqlValue> {
+        self.values.get(index)
+    }
+
+    /// Gets the value of the column with the provided name.
+    pub fn get_by_name(&self, name: &str) -> Option<&SqlValue> {
+        name.position(self).and_then(|i| self.values.get(i))
+    }
+
+    /// Gets a value from the row and converts it into a concrete Rust type.
+    pub fn try_get<T, I>(&self, index: I) -> Result<T, RowConversionError>
+    where
+        T: RecordField,
+        I: RowIndex,
+    {
+        let value = self.lookup(&index)?.clone();
+        let field = T::from_sql(value)?;
+        Ok(field)
+    }
+
+    /// Moves a value out of the row, leaving [SqlValue::Null] in its place.
+    pub fn take<I>(&mut self, index: I) -> Result<SqlValue, RowConversionError>
+    where
+        I: RowIndex,
+    {
+        let position = index
+            .position(self)
+            .ok_or_else(|| RowConversionError::ColumnNotFound {
+                column: index.to_string(),
+            })?;
+
+        Ok(core::mem::replace(
+            &mut self.values[position],
+            SqlValue::Null,
+        ))
+    }
+
+    /// Iterates over the values of the row without consuming it.
+    pub fn iter(&self) -> core::slice::Iter<'_, SqlValue> {
+        self.values.iter()
+    }
+
+    fn lookup<I>(&self, index: &I) -> Result<&SqlValue, RowConversionError>
+    where
+        I: RowIndex,
+    {
+        index
+            .position(self)
+            .and_then(|i| self.values.get(i))
+            .ok_or_else(|| RowConversionError::ColumnNotFound {
+                column: index.to_string(),
+            })
+    }
+}
 
 impl FromIterator<SqlValue> for SqlRow {
     fn from_iter<T: IntoIterator<Item = SqlValue>>(iter: T) -> Self {
-        Self(Vec::from_iter(iter))
+        Self {
+            columns: Arc::from(Vec::new()),
+            values: Vec::from_iter(iter),
+        }
     }
 }
 
@@ -16,6 +114,161 @@ impl IntoIterator for SqlRow {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.values.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SqlRow {
+    type Item = &'a SqlValue;
+
+    type IntoIter = core::slice::Iter<'a, SqlValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A type which can be used to locate a value within a [SqlRow].
+pub trait RowIndex: Display {
+    /// Finds the position of the value within the row.
+    fn position(&self, row: &SqlRow) -> Option<usize>;
+}
+
+impl RowIndex for usize {
+    fn position(&self, row: &SqlRow) -> Option<usize> {
+        if *self < row.len() {
+            Some(*self)
+        } else {
+            None
+        }
+    }
+}
+
+impl RowIndex for str {
+    fn position(&self, row: &SqlRow) -> Option<usize> {
+        row.columns.iter().position(|column| column == self)
+    }
+}
+
+impl RowIndex for String {
+    fn position(&self, row: &SqlRow) -> Option<usize> {
+        self.as_str().position(row)
+    }
+}
+
+impl<T> RowIndex for &T
+where
+    T: RowIndex + ?Sized,
+{
+    fn position(&self, row: &SqlRow) -> Option<usize> {
+        T::position(self, row)
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "sql-value-i64", feature = "sql-value-string"))]
+mod tests {
+    use super::*;
+    use crate::errors::FieldConversionError;
+
+    fn user() -> SqlRow {
+        SqlRow::new(
+            Arc::from(vec![String::from("id"), String::from("name")]),
+            vec![SqlValue::Signed64(1), SqlValue::String(String::from("ada"))],
+        )
+    }
+
+    fn column_not_found(column: &str) -> RowConversionError {
+        RowConversionError::ColumnNotFound {
+            column: column.to_owned(),
+        }
+    }
+
+    /// Errors are compared by their messages, since they do not implement
+    /// `PartialEq`.
+    fn message<T>(result: Result<T, RowConversionError>) -> String {
+        match result {
+            Ok(_) => String::from("no error"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn counts_its_values() {
+        assert_eq!(user().len(), 2);
+        assert!(!user().is_empty());
+        assert!(SqlRow::from_iter(vec![]).is_empty());
+    }
+
+    #[test]
+    fn gets_values_by_position_or_name() {
+        let row = user();
+        assert_eq!(row.get(0), Some(&SqlValue::Signed64(1)));
+        assert_eq!(row.get(2), None);
+        assert_eq!(
+            row.get_by_name("name"),
+            Some(&SqlValue::String(String::from("ada")))
+        );
+        assert_eq!(row.get_by_name("email"), None);
+
+        // Rows without column names can only be indexed by position.
+        let positional = SqlRow::from_iter(vec![SqlValue::Signed64(1)]);
+        assert_eq!(positional.get_by_name("id"), None);
+        assert_eq!(positional.get(0), Some(&SqlValue::Signed64(1)));
+    }
+
+    #[test]
+    fn converts_values_by_any_index() {
+        let row = user();
+        assert_eq!(row.try_get::<i64, _>(0).unwrap(), 1);
+        assert_eq!(row.try_get::<String, _>("name").unwrap(), "ada");
+        assert_eq!(
+            row.try_get::<String, _>(String::from("name")).unwrap(),
+            "ada"
+        );
+        assert_eq!(row.try_get::<String, _>(&1).unwrap(), "ada");
+    }
+
+    #[test]
+    fn reports_indexes_which_are_out_of_range_or_unknown() {
+        let row = user();
+        assert_eq!(
+            message(row.try_get::<i64, _>(2)),
+            column_not_found("2").to_string()
+        );
+        assert_eq!(
+            message(row.try_get::<i64, _>("email")),
+            column_not_found("email").to_string()
+        );
+
+        let mut row = user();
+        assert_eq!(message(row.take(5)), column_not_found("5").to_string());
+        assert_eq!(
+            message(row.take("email")),
+            column_not_found("email").to_string()
+        );
+    }
+
+    #[test]
+    fn reports_values_of_the_wrong_type() {
+        let err = user().try_get::<i64, _>("name").unwrap_err();
+        match err {
+            RowConversionError::FieldConversionError(err) => {
+                assert_eq!(err, FieldConversionError::IncorrectType)
+            }
+            other => panic!("expected a field conversion error, but got {}", other),
+        }
+    }
+
+    #[test]
+    fn takes_values_leaving_null_behind() {
+        let mut row = user();
+        assert_eq!(
+            row.take("name").unwrap(),
+            SqlValue::String(String::from("ada"))
+        );
+        assert_eq!(row.get_by_name("name"), Some(&SqlValue::Null));
+        assert_eq!(row.take(1).unwrap(), SqlValue::Null);
+        assert_eq!(row.len(), 2);
     }
 }
@@ -0,0 +1,218 @@
+/// Splits SQL into the statements it contains, which are separated by
+/// semicolons. Semicolons within quoted strings and identifiers, comments and
+/// postgres dollar quoted strings do not end a statement. Statements which
+/// only hold whitespace or comments are dropped.
+///
+/// Backslashes only escape quotes within postgres `E'...'` strings, so the
+/// statements of a MySQL script should not rely on them. The semicolons within
+/// the body of a SQLite trigger also end a statement.
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b';' => {
+                if has_code {
+                    statements.push(sql[start..i].trim());
+                }
+
+                start = i + 1;
+                has_code = false;
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = match sql[i..].find('\n') {
+                    Some(end) => i + end + 1,
+                    None => bytes.len(),
+                };
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+            }
+            quote @ (b'\'' | b'"' | b'`') => {
+                let escapes = quote == b'\'' && is_escape_string(bytes, i);
+                i = skip_quoted(bytes, i, escapes);
+                has_code = true;
+            }
+            b'$' => {
+                i = skip_dollar_quoted(sql, i);
+                has_code = true;
+            }
+            byte => {
+                has_code |= !byte.is_ascii_whitespace();
+                i += 1;
+            }
+        }
+    }
+
+    if has_code {
+        statements.push(sql[start..].trim());
+    }
+
+    statements
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || !byte.is_ascii()
+}
+
+/// Checks if the quote at `i` opens an `E'...'` string.
+fn is_escape_string(bytes: &[u8], i: usize) -> bool {
+    match i.checked_sub(1).map(|prefix| bytes[prefix]) {
+        Some(b'E') | Some(b'e') => i < 2 || !is_identifier_byte(bytes[i - 2]),
+        _ => false,
+    }
+}
+
+/// Returns the index after the quoted string or identifier opened at `i`. A
+/// doubled quote is part of the string.
+fn skip_quoted(bytes: &[u8], i: usize, escapes: bool) -> usize {
+    let quote = bytes[i];
+    let mut i = i + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 2,
+            byte if byte == quote => {
+                if bytes.get(i + 1) == Some(&quote) {
+                    i += 2;
+                } else {
+                    return i + 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Returns the index after the block comment opened at `i`. Like postgres,
+/// block comments nest.
+fn skip_block_comment(bytes: &[u8], i: usize) -> usize {
+    let mut depth = 0;
+    let mut i = i;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'*')) => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', Some(b'/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Returns the index after the dollar quoted string opened at `i`, or the
+/// index after the dollar sign if it does not open one, like a placeholder.
+fn skip_dollar_quoted(sql: &str, i: usize) -> usize {
+    let bytes = sql.as_bytes();
+    if i > 0 && is_identifier_byte(bytes[i - 1]) {
+        return i + 1;
+    }
+
+    // The tag of a dollar quote is an identifier, which cannot start with a
+    // digit, so `$1` is a placeholder.
+    let tag_end = bytes[i + 1..]
+        .iter()
+        .position(|&byte| !(byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()))
+        .map_or(bytes.len(), |end| i + 1 + end);
+    let starts_with_digit = matches!(bytes.get(i + 1), Some(byte) if byte.is_ascii_digit());
+    if bytes.get(tag_end) != Some(&b'$') || starts_with_digit {
+        return i + 1;
+    }
+
+    let delimiter = &sql[i..=tag_end];
+    match sql[tag_end + 1..].find(delimiter) {
+        Some(end) => tag_end + 1 + end + delimiter.len(),
+        None => bytes.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_statements;
+
+    #[test]
+    fn splits_on_semicolons_outside_of_dollar_quotes() {
+        let sql = "CREATE FUNCTION f() RETURNS INT AS $body$ SELECT 1; $body$ LANGUAGE sql;\
+            SELECT $$a;b$$; SELECT $1; SELECT $2";
+        assert_eq!(
+            split_statements(sql),
+            [
+                "CREATE FUNCTION f() RETURNS INT AS $body$ SELECT 1; $body$ LANGUAGE sql",
+                "SELECT $$a;b$$",
+                "SELECT $1",
+                "SELECT $2",
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_treat_placeholders_as_dollar_quotes() {
+        // `$1` is not a tag, so the semicolon after it ends the statement
+        // rather than everything up to the next `$1`.
+        assert_eq!(
+            split_statements("SELECT $1; SELECT $1"),
+            ["SELECT $1", "SELECT $1"]
+        );
+        assert_eq!(
+            split_statements("SELECT a$b$c; SELECT 1"),
+            ["SELECT a$b$c", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn skips_escaped_quotes_in_escape_strings() {
+        assert_eq!(
+            split_statements(r"SELECT E'it\'s;'; SELECT e'\\'; SELECT 1"),
+            [r"SELECT E'it\'s;'", r"SELECT e'\\'", "SELECT 1"]
+        );
+
+        // Outside of `E'...'`, a backslash does not escape the quote.
+        assert_eq!(
+            split_statements(r"SELECT 'a\'; SELECT 1"),
+            [r"SELECT 'a\'", "SELECT 1"]
+        );
+        assert_eq!(
+            split_statements(r"SELECT type'a\'; SELECT 1"),
+            [r"SELECT type'a\'", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn skips_nested_block_comments() {
+        assert_eq!(
+            split_statements("SELECT 1 /* a /* b; */ c; */; SELECT 2"),
+            ["SELECT 1 /* a /* b; */ c; */", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn skips_doubled_quotes() {
+        assert_eq!(
+            split_statements(r#"SELECT 'it''s;'; SELECT "a"";b"; SELECT `c``;`"#),
+            [r#"SELECT 'it''s;'"#, r#"SELECT "a"";b""#, "SELECT `c``;`"]
+        );
+    }
+
+    #[test]
+    fn drops_statements_of_only_whitespace_or_comments() {
+        assert_eq!(
+            split_statements("SELECT 1; -- one;\n ; /* two; */ ;\n"),
+            ["SELECT 1"]
+        );
+    }
+}
@@ -1,8 +1,14 @@
 use core::fmt::Display;
 use core::fmt::Formatter;
 use core::fmt::Result as FmtResult;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 /// A raw SQL value.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum SqlValue {
     /// A simple `true` or `false` value.
     #[cfg(feature = "sql-value-bool")]
@@ -85,3 +91,90 @@ impl Display for SqlValue {
         }
     }
 }
+
+impl SqlValue {
+    /// Gets the kind of value without its payload.
+    pub fn kind(&self) -> SqlValueKind {
+        match self {
+            #[cfg(feature = "sql-value-bool")]
+            SqlValue::Boolean(_) => SqlValueKind::Boolean,
+            #[cfg(feature = "sql-value-f32")]
+            SqlValue::Float32(_) => SqlValueKind::Float32,
+            #[cfg(feature = "sql-value-f64")]
+            SqlValue::Float64(_) => SqlValueKind::Float64,
+            SqlValue::Null => SqlValueKind::Null,
+            #[cfg(feature = "sql-value-string")]
+            SqlValue::String(_) => SqlValueKind::String,
+            #[cfg(feature = "sql-value-i8")]
+            SqlValue::Signed8(_) => SqlValueKind::Signed8,
+            #[cfg(feature = "sql-value-i16")]
+            SqlValue::Signed16(_) => SqlValueKind::Signed16,
+            #[cfg(feature = "sql-value-i32")]
+            SqlValue::Signed32(_) => SqlValueKind::Signed32,
+            #[cfg(feature = "sql-value-i64")]
+            SqlValue::Signed64(_) => SqlValueKind::Signed64,
+            #[cfg(feature = "sql-value-i128")]
+            SqlValue::Signed128(_) => SqlValueKind::Signed128,
+            #[cfg(feature = "sql-value-u8")]
+            SqlValue::Unsigned8(_) => SqlValueKind::Unsigned8,
+            #[cfg(feature = "sql-value-u16")]
+            SqlValue::Unsigned16(_) => SqlValueKind::Unsigned16,
+            #[cfg(feature = "sql-value-u32")]
+            SqlValue::Unsigned32(_) => SqlValueKind::Unsigned32,
+            #[cfg(feature = "sql-value-u64")]
+            SqlValue::Unsigned64(_) => SqlValueKind::Unsigned64,
+            #[cfg(feature = "sql-value-u128")]
+            SqlValue::Unsigned128(_) => SqlValueKind::Unsigned128,
+        }
+    }
+}
+
+/// The kind of a [SqlValue], without the value itself.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SqlValueKind {
+    /// The kind of [SqlValue::Boolean].
+    #[cfg(feature = "sql-value-bool")]
+    Boolean,
+    /// The kind of [SqlValue::Float32].
+    #[cfg(feature = "sql-value-f32")]
+    Float32,
+    /// The kind of [SqlValue::Float64].
+    #[cfg(feature = "sql-value-f64")]
+    Float64,
+    /// The kind of [SqlValue::Null].
+    Null,
+    /// The kind of [SqlValue::String].
+    #[cfg(feature = "sql-value-string")]
+    String,
+    /// The kind of [SqlValue::Signed8].
+    #[cfg(feature = "sql-value-i8")]
+    Signed8,
+    /// The kind of [SqlValue::Signed16].
+    #[cfg(feature = "sql-value-i16")]
+    Signed16,
+    /// The kind of [SqlValue::Signed32].
+    #[cfg(feature = "sql-value-i32")]
+    Signed32,
+    /// The kind of [SqlValue::Signed64].
+    #[cfg(feature = "sql-value-i64")]
+    Signed64,
+    /// The kind of [SqlValue::Signed128].
+    #[cfg(feature = "sql-value-i128")]
+    Signed128,
+    /// The kind of [SqlValue::Unsigned8].
+    #[cfg(feature = "sql-value-u8")]
+    Unsigned8,
+    /// The kind of [SqlValue::Unsigned16].
+    #[cfg(feature = "sql-value-u16")]
+    Unsigned16,
+    /// The kind of [SqlValue::Unsigned32].
+    #[cfg(feature = "sql-value-u32")]
+    Unsigned32,
+    /// The kind of [SqlValue::Unsigned64].
+    #[cfg(feature = "sql-value-u64")]
+    Unsigned64,
+    /// The kind of [SqlValue::Unsigned128].
+    #[cfg(feature = "sql-value-u128")]
+    Unsigned128,
+}
@@ -0,0 +1,378 @@
+//! Types and helpers for testing code which uses a
+//! [Connection](crate::connections::Connection), with or without a live
+//! database.
+
+#![cfg(feature = "testing")]
+
+#[cfg(feature = "cassettes")]
+mod cassettes;
+mod databases;
+mod queries;
+
+#[cfg(feature = "cassettes")]
+pub use self::cassettes::RecordingExecutor;
+#[cfg(feature = "cassettes")]
+pub use self::cassettes::ReplayExecutor;
+pub use self::databases::Isolation;
+pub use self::databases::TestDatabase;
+pub use self::queries::query;
+pub use self::queries::run;
+use crate::errors::ConnectionError;
+use crate::errors::SqlExecutionError;
+use crate::executors::ResultIter;
+use crate::executors::ResultSet;
+use crate::executors::SqlExecutor;
+use crate::sql::split_statements;
+use crate::sql::ColumnDescription;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use crate::Script;
+use crate::ScriptInfo;
+use core::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+/// A statement which was executed by a [MockExecutor].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Execution {
+    sql: String,
+    params: Vec<SqlValue>,
+    script: Option<ScriptInfo>,
+}
+
+impl Execution {
+    /// The SQL which was executed.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// The parameters which were bound to the SQL.
+    pub fn params(&self) -> &[SqlValue] {
+        &self.params
+    }
+
+    /// The script the SQL was compiled from, if it was compiled from one.
+    pub fn script(&self) -> Option<&ScriptInfo> {
+        self.script.as_ref()
+    }
+}
+
+/// Determines which statements a programmed response applies to.
+#[derive(Clone, Debug)]
+enum Matcher {
+    Sql(String),
+    Script(&'static str),
+}
+
+impl Matcher {
+    fn matches(&self, sql: &str, script: Option<&ScriptInfo>) -> bool {
+        match self {
+            Matcher::Sql(expected) => expected == sql,
+            Matcher::Script(name) => script.map(ScriptInfo::name) == Some(name),
+        }
+    }
+}
+
+/// The programmed result of a statement.
+#[derive(Clone, Debug)]
+enum Response {
+    Rows(Vec<SqlRow>),
+    Affected(u64),
+    Error(String),
+}
+
+#[derive(Default)]
+struct MockState {
+    executions: Vec<Execution>,
+    responses: Vec<(Matcher, Response)>,
+    next_script: Option<ScriptInfo>,
+}
+
+/// An executor which records every statement it executes instead of running
+/// it against a database. Statements return no rows unless a response has
+/// been programmed with [MockExecutor::when] or [MockExecutor::when_sql].
+///
+/// Clones of a mock share their state, so a clone can be kept to inspect the
+/// statements after the original has been moved into a connection.
+#[derive(Clone, Default)]
+pub struct MockExecutor {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockExecutor {
+    /// Creates a mock which has not executed any statements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Programs the response to statements compiled from scripts of type `S`.
+    pub fn when<S>(&self) -> Expectation<'_>
+    where
+        S: Script,
+    {
+        Expectation {
+            mock: self,
+            matcher: Matcher::Script(ScriptInfo::of::<S>().name()),
+        }
+    }
+
+    /// Programs the response to statements with exactly the provided SQL.
+    pub fn when_sql(&self, sql: &str) -> Expectation<'_> {
+        Expectation {
+            mock: self,
+            matcher: Matcher::Sql(sql.to_owned()),
+        }
+    }
+
+    /// All of the statements which have been executed, in order.
+    pub fn executions(&self) -> Vec<Execution> {
+        self.state().executions.clone()
+    }
+
+    /// The statements which were compiled from scripts of type `S`.
+    pub fn executions_of<S>(&self) -> Vec<Execution>
+    where
+        S: Script,
+    {
+        let matcher = Matcher::Script(ScriptInfo::of::<S>().name());
+        self.state()
+            .executions
+            .iter()
+            .filter(|execution| matcher.matches(&execution.sql, execution.script()))
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets all of the statements which have been executed. Programmed
+    /// responses are kept.
+    pub fn clear(&self) {
+        self.state().executions.clear();
+    }
+
+    /// Panics if no statement was compiled from a script of type `S`.
+    pub fn assert_executed<S>(&self)
+    where
+        S: Script,
+    {
+        if self.executions_of::<S>().is_empty() {
+            panic!(
+                "expected {} to be executed, but the executed statements were {:#?}",
+                ScriptInfo::of::<S>().name(),
+                self.executed_sql()
+            );
+        }
+    }
+
+    /// Panics if a statement was compiled from a script of type `S`.
+    pub fn assert_not_executed<S>(&self)
+    where
+        S: Script,
+    {
+        let executions = self.executions_of::<S>();
+        if !executions.is_empty() {
+            panic!(
+                "expected {} not to be executed, but it was executed {} time(s)",
+                ScriptInfo::of::<S>().name(),
+                executions.len()
+            );
+        }
+    }
+
+    /// Panics if no statement with exactly the provided SQL was executed.
+    pub fn assert_executed_sql(&self, sql: &str) {
+        let executed_sql = self.executed_sql();
+        if !executed_sql.iter().any(|executed| executed == sql) {
+            panic!(
+                "expected {:?} to be executed, but the executed statements were {:#?}",
+                sql, executed_sql
+            );
+        }
+    }
+
+    fn executed_sql(&self) -> Vec<String> {
+        self.state()
+            .executions
+            .iter()
+            .map(|execution| execution.sql.clone())
+            .collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A test which panicked while holding the lock leaves the state intact.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Records the statement and finds the response which was most recently
+    /// programmed for it.
+    fn record(&mut self, sql: &str, params: &[SqlValue]) -> Option<Response> {
+        let mut state = self.state();
+        let script = state.next_script.take();
+        let response = state
+            .responses
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.matches(sql, script.as_ref()))
+            .map(|(_, response)| response.clone());
+
+        state.executions.push(Execution {
+            sql: sql.to_owned(),
+            params: params.to_vec(),
+            script,
+        });
+
+        response
+    }
+}
+
+impl SqlExecutor for MockExecutor {
+    fn connect(_: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new())
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        match self.record(sql, params) {
+            Some(Response::Rows(rows)) => Ok(ResultSet::new(
+                describe_rows(rows.first()),
+                Box::new(rows.into_iter().map(Ok)),
+            )),
+            Some(Response::Error(message)) => Err(SqlExecutionError::Other {
+                err: message.into(),
+            }),
+            Some(Response::Affected(_)) | None => {
+                let rows: ResultIter<'a> = Box::new(std::iter::empty());
+                Ok(rows.into())
+            }
+        }
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        match self.record(sql, params) {
+            Some(Response::Rows(rows)) => Ok(rows.len() as u64),
+            Some(Response::Affected(affected)) => Ok(affected),
+            Some(Response::Error(message)) => Err(SqlExecutionError::Other {
+                err: message.into(),
+            }),
+            None => Ok(0),
+        }
+    }
+
+    fn execute_sql_batch(&mut self, statements: &[String]) -> Vec<Result<u64, SqlExecutionError>> {
+        // Every statement of the batch was compiled from the same script.
+        let script = self.state().next_script.take();
+        statements
+            .iter()
+            .map(|sql| {
+                self.state().next_script = script;
+                self.execute_sql_affected(sql, &[])
+            })
+            .collect()
+    }
+
+    fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        let script = self.state().next_script.take();
+        statements
+            .iter()
+            .map(|sql| {
+                self.state().next_script = script;
+                self.execute_sql_affected(sql, &[])
+            })
+            .collect()
+    }
+
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        // Every statement of the script is attributed to it, and a statement
+        // returns a result set if rows were programmed for it, even none.
+        let script = self.state().next_script.take();
+        let mut sets = Vec::new();
+        for statement in split_statements(sql) {
+            self.state().next_script = script;
+            match self.record(statement, &[]) {
+                Some(Response::Rows(rows)) => sets.push(ResultSet::new(
+                    describe_rows(rows.first()),
+                    Box::new(rows.into_iter().map(Ok)),
+                )),
+                Some(Response::Error(message)) => {
+                    return Err(SqlExecutionError::Other {
+                        err: message.into(),
+                    })
+                }
+                Some(Response::Affected(_)) | None => {}
+            }
+        }
+
+        Ok(sets)
+    }
+
+    fn on_script(&mut self, script: &ScriptInfo) {
+        self.state().next_script = Some(*script);
+    }
+
+    // Statements never run long enough to time out, and the timeout of a
+    // script can be checked through Execution::script.
+    fn set_timeout(&mut self, _: Option<Duration>) -> Result<(), SqlExecutionError> {
+        Ok(())
+    }
+}
+
+/// Programs the response of a [MockExecutor] to the matching statements. If
+/// several responses match a statement, the most recently programmed one is
+/// used.
+#[must_use = "a response is only programmed once a method is called"]
+pub struct Expectation<'m> {
+    mock: &'m MockExecutor,
+    matcher: Matcher,
+}
+
+impl<'m> Expectation<'m> {
+    /// The matching statements return the provided rows.
+    pub fn returns(self, rows: Vec<SqlRow>) {
+        self.respond(Response::Rows(rows));
+    }
+
+    /// The matching statements return no rows and report that the provided
+    /// number of rows were affected.
+    pub fn affects(self, affected: u64) {
+        self.respond(Response::Affected(affected));
+    }
+
+    /// The matching statements fail with the provided message.
+    pub fn fails(self, message: &str) {
+        self.respond(Response::Error(message.to_owned()));
+    }
+
+    fn respond(self, response: Response) {
+        self.mock.state().responses.push((self.matcher, response));
+    }
+}
+
+/// Describes the columns of canned rows by the names of the first row, since
+/// nothing else is known about them.
+fn describe_rows(first_row: Option<&SqlRow>) -> Arc<[ColumnDescription]> {
+    first_row
+        .map(|row| {
+            row.columns()
+                .iter()
+                .map(|name| ColumnDescription::new(name))
+                .collect()
+        })
+        .unwrap_or_else(|| Arc::from(Vec::new()))
+}
@@ -0,0 +1,519 @@
+use crate::errors::CassetteError;
+use crate::errors::ConnectionError;
+use crate::errors::RawRowConversionError;
+use crate::errors::RowConversionError;
+use crate::errors::SqlExecutionError;
+use crate::executors::CancelHandle;
+use crate::executors::ResultIter;
+use crate::executors::ResultSet;
+use crate::executors::SqlExecutor;
+use crate::sql::split_statements;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+use crate::testing::describe_rows;
+use crate::transactions::TransactionOptions;
+use crate::ScriptInfo;
+use core::time::Duration;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The environment variable which names the cassette a [RecordingExecutor]
+/// records into when it is created through [SqlExecutor::connect].
+const CASSETTE_VARIABLE: &str = "WORM_CASSETTE";
+
+/// The statements recorded by a [RecordingExecutor], in the order they were
+/// executed.
+#[derive(Default, Deserialize, Serialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+/// A statement and the response the database gave to it.
+#[derive(Deserialize, Serialize)]
+struct Interaction {
+    sql: String,
+    params: Vec<SqlValue>,
+    response: RecordedResponse,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedResponse {
+    Rows(Vec<RecordedRow>),
+    Sets(Vec<Vec<RecordedRow>>),
+    Affected(u64),
+    Error(String),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedRow {
+    Row(SqlRow),
+    Error(String),
+}
+
+impl RecordedRow {
+    fn record(row: &Result<SqlRow, RowConversionError>) -> Self {
+        match row {
+            Ok(row) => RecordedRow::Row(row.clone()),
+            Err(err) => RecordedRow::Error(err.to_string()),
+        }
+    }
+
+    fn replay(self) -> Result<SqlRow, RowConversionError> {
+        match self {
+            RecordedRow::Row(row) => Ok(row),
+            RecordedRow::Error(message) => Err(RawRowConversionError::Other {
+                err: message.into(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// An executor which runs statements with another executor and records each
+/// statement, its parameters and the response of the database into a
+/// cassette file. The cassette is written when the executor is dropped, so it
+/// is complete even if the test fails part way through, or when
+/// [RecordingExecutor::flush] is called.
+///
+/// When created through [SqlExecutor::connect], the connection string is
+/// passed to the wrapped executor and the cassette is written to the path in
+/// the `WORM_CASSETTE` environment variable.
+pub struct RecordingExecutor<E> {
+    executor: E,
+    recorder: Recorder,
+}
+
+impl<E> RecordingExecutor<E>
+where
+    E: SqlExecutor,
+{
+    /// Creates an executor which records the statements run by `executor`
+    /// into the cassette at `path`. An existing cassette is replaced.
+    pub fn new(executor: E, path: impl Into<PathBuf>) -> Self {
+        Self {
+            executor,
+            recorder: Recorder {
+                path: path.into(),
+                cassette: Cassette::default(),
+                unsaved: true,
+            },
+        }
+    }
+
+    /// Writes the statements recorded so far to the cassette. Errors which
+    /// occur while the executor is dropped cannot be reported, so this can be
+    /// called to check that the cassette was written.
+    pub fn flush(&mut self) -> Result<(), CassetteError> {
+        self.recorder.flush()
+    }
+
+    /// Stops recording and returns the wrapped executor. The cassette is
+    /// written before it is returned.
+    pub fn into_inner(self) -> E {
+        self.executor
+    }
+
+    fn record(&mut self, sql: &str, params: &[SqlValue], response: RecordedResponse) {
+        self.recorder.cassette.interactions.push(Interaction {
+            sql: sql.to_owned(),
+            params: params.to_vec(),
+            response,
+        });
+
+        self.recorder.unsaved = true;
+    }
+
+    /// Records a statement which does not return rows.
+    fn record_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+        result: Result<u64, SqlExecutionError>,
+    ) -> Result<u64, SqlExecutionError> {
+        let response = match &result {
+            Ok(affected) => RecordedResponse::Affected(*affected),
+            Err(err) => RecordedResponse::Error(error_message(err)),
+        };
+
+        self.record(sql, params, response);
+        result
+    }
+}
+
+impl<E> SqlExecutor for RecordingExecutor<E>
+where
+    E: SqlExecutor,
+{
+    fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let path = std::env::var_os(CASSETTE_VARIABLE).ok_or_else(|| {
+            let message = format!("{} must name the cassette to record", CASSETTE_VARIABLE);
+            ConnectionError::with_source(connection_string.to_owned(), message.into())
+        })?;
+
+        let executor = E::connect(connection_string)?;
+        Ok(Self::new(executor, path))
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        // The rows are read up front so that they can be written to the
+        // cassette before they are returned.
+        let result = self
+            .executor
+            .execute_sql(sql, params)
+            .map(|mut result_set| {
+                let rows = result_set.by_ref().collect::<Vec<_>>();
+                (result_set.into_columns(), rows)
+            });
+
+        let (columns, rows) = match result {
+            Ok(rows) => rows,
+            Err(err) => {
+                self.record(sql, params, RecordedResponse::Error(error_message(&err)));
+                return Err(err);
+            }
+        };
+
+        // A statement which returns neither columns nor rows, like the setup
+        // of a script with several statements, is not replayed as a result
+        // set.
+        let response = if columns.is_empty() && rows.is_empty() {
+            RecordedResponse::Affected(0)
+        } else {
+            RecordedResponse::Rows(rows.iter().map(RecordedRow::record).collect())
+        };
+
+        self.record(sql, params, response);
+        Ok(ResultSet::new(columns, Box::new(rows.into_iter())))
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        let result = self.executor.execute_sql_affected(sql, params);
+        self.record_affected(sql, params, result)
+    }
+
+    fn execute_sql_batch(&mut self, statements: &[String]) -> Vec<Result<u64, SqlExecutionError>> {
+        let results = self.executor.execute_sql_batch(statements);
+        statements
+            .iter()
+            .zip(results)
+            .map(|(sql, result)| self.record_affected(sql, &[], result))
+            .collect()
+    }
+
+    fn execute_sql_batch_atomic(
+        &mut self,
+        statements: &[String],
+    ) -> Result<Vec<u64>, SqlExecutionError> {
+        match self.executor.execute_sql_batch_atomic(statements) {
+            Ok(affected) => {
+                for (sql, affected) in statements.iter().zip(&affected) {
+                    self.record(sql, &[], RecordedResponse::Affected(*affected));
+                }
+
+                Ok(affected)
+            }
+            // Which statement failed is not known, so the error is recorded
+            // against the first one, where a replay of the batch then stops.
+            Err(err) => match statements.first() {
+                Some(sql) => self.record_affected(sql, &[], Err(err)).map(|_| vec![]),
+                None => Err(err),
+            },
+        }
+    }
+
+    fn execute_statements(&mut self, sql: &str) -> Result<(), SqlExecutionError> {
+        let result = self.executor.execute_statements(sql).map(|_| 0);
+        self.record_affected(sql, &[], result).map(|_| ())
+    }
+
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        // Which statements the result sets belong to is not known, so the SQL
+        // is recorded as a whole with every result set it returned.
+        let sets = match self.executor.execute_sql_multi(sql) {
+            Ok(sets) => sets,
+            Err(err) => {
+                self.record(sql, &[], RecordedResponse::Error(error_message(&err)));
+                return Err(err);
+            }
+        };
+
+        let sets = sets
+            .into_iter()
+            .map(|mut result_set| {
+                let rows = result_set.by_ref().collect::<Vec<_>>();
+                (result_set.into_columns(), rows)
+            })
+            .collect::<Vec<_>>();
+        let recorded = sets
+            .iter()
+            .map(|(_, rows)| rows.iter().map(RecordedRow::record).collect())
+            .collect();
+
+        self.record(sql, &[], RecordedResponse::Sets(recorded));
+        Ok(sets
+            .into_iter()
+            .map(|(columns, rows)| ResultSet::new(columns, Box::new(rows.into_iter())))
+            .collect())
+    }
+
+    fn on_script(&mut self, script: &ScriptInfo) {
+        self.executor.on_script(script);
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SqlExecutionError> {
+        self.executor.set_timeout(timeout)
+    }
+
+    fn cancel_handle(&self) -> Option<CancelHandle> {
+        self.executor.cancel_handle()
+    }
+
+    // The transaction statements are recorded with the SQL the default
+    // implementations of these methods execute, so that a ReplayExecutor
+    // finds them in the cassette.
+
+    fn begin_transaction(&mut self, options: &TransactionOptions) -> Result<(), SqlExecutionError> {
+        let result = self.executor.begin_transaction(options).map(|_| 0);
+        self.record_affected(&options.begin_sql(), &[], result)
+            .map(|_| ())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        let result = self.executor.commit_transaction().map(|_| 0);
+        self.record_affected("COMMIT", &[], result).map(|_| ())
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), SqlExecutionError> {
+        let result = self.executor.rollback_transaction().map(|_| 0);
+        self.record_affected("ROLLBACK", &[], result).map(|_| ())
+    }
+
+    fn create_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        let result = self.executor.create_savepoint(name).map(|_| 0);
+        self.record_affected(&format!("SAVEPOINT {}", name), &[], result)
+            .map(|_| ())
+    }
+
+    fn release_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        let result = self.executor.release_savepoint(name).map(|_| 0);
+        self.record_affected(&format!("RELEASE SAVEPOINT {}", name), &[], result)
+            .map(|_| ())
+    }
+
+    fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), SqlExecutionError> {
+        let result = self.executor.rollback_to_savepoint(name).map(|_| 0);
+        self.record_affected(&format!("ROLLBACK TO SAVEPOINT {}", name), &[], result)
+            .map(|_| ())
+    }
+}
+
+/// The cassette a [RecordingExecutor] records into, which is written once the
+/// recording is dropped.
+struct Recorder {
+    path: PathBuf,
+    cassette: Cassette,
+    unsaved: bool,
+}
+
+impl Recorder {
+    fn flush(&mut self) -> Result<(), CassetteError> {
+        if !self.unsaved {
+            return Ok(());
+        }
+
+        let json = serde_json::to_vec_pretty(&self.cassette)?;
+        std::fs::write(&self.path, json)?;
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // The error cannot be returned, and panicking while a failed test
+        // unwinds would abort it, so RecordingExecutor::flush reports it.
+        let _ = self.flush();
+    }
+}
+
+/// Replays recorded rows, describing their columns by the names of the
+/// first row.
+fn replay_rows(rows: Vec<RecordedRow>) -> ResultSet<'static> {
+    let rows = rows
+        .into_iter()
+        .map(RecordedRow::replay)
+        .collect::<Vec<_>>();
+    let first_row = rows.iter().find_map(|row| row.as_ref().ok());
+    ResultSet::new(describe_rows(first_row), Box::new(rows.into_iter()))
+}
+
+/// The message of an execution error, without the context which is added
+/// again when the error is replayed.
+fn error_message(err: &SqlExecutionError) -> String {
+    match err {
+        SqlExecutionError::Other { err } => err.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// An executor which serves the responses recorded by a [RecordingExecutor]
+/// instead of connecting to a database. Statements must be executed in the
+/// order they were recorded, with the same parameters, otherwise they fail
+/// with a [CassetteError].
+///
+/// When created through [SqlExecutor::connect], the connection string is the
+/// path of the cassette.
+pub struct ReplayExecutor {
+    interactions: std::vec::IntoIter<Interaction>,
+}
+
+impl ReplayExecutor {
+    /// Loads the cassette at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        let json = std::fs::read(path)?;
+        let cassette: Cassette = serde_json::from_slice(&json)?;
+        Ok(Self {
+            interactions: cassette.interactions.into_iter(),
+        })
+    }
+
+    /// Checks if every recorded statement has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.interactions.len() == 0
+    }
+
+    fn replay(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<RecordedResponse, CassetteError> {
+        let interaction =
+            self.interactions
+                .as_slice()
+                .first()
+                .ok_or_else(|| CassetteError::Unrecorded {
+                    sql: sql.to_owned(),
+                })?;
+
+        if interaction.sql != sql || interaction.params != params {
+            return Err(CassetteError::Mismatch {
+                expected_sql: interaction.sql.clone(),
+                expected_params: interaction.params.clone(),
+                sql: sql.to_owned(),
+                params: params.to_vec(),
+            });
+        }
+
+        let interaction = self.interactions.next().unwrap();
+        Ok(interaction.response)
+    }
+}
+
+impl SqlExecutor for ReplayExecutor {
+    fn connect(connection_string: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Self::open(connection_string).map_err(|err| {
+            ConnectionError::with_source(connection_string.to_owned(), Box::new(err))
+        })
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        match self.replay(sql, params)? {
+            RecordedResponse::Rows(rows) => Ok(replay_rows(rows)),
+            RecordedResponse::Sets(mut sets) if sets.len() == 1 => Ok(replay_rows(sets.remove(0))),
+            RecordedResponse::Sets(_) | RecordedResponse::Affected(_) => {
+                let rows: ResultIter<'a> = Box::new(std::iter::empty());
+                Ok(rows.into())
+            }
+            RecordedResponse::Error(message) => Err(SqlExecutionError::Other {
+                err: message.into(),
+            }),
+        }
+    }
+
+    fn execute_sql_affected(
+        &mut self,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, SqlExecutionError> {
+        match self.replay(sql, params)? {
+            RecordedResponse::Rows(rows) => Ok(rows.len() as u64),
+            RecordedResponse::Sets(sets) => Ok(sets.iter().map(Vec::len).sum::<usize>() as u64),
+            RecordedResponse::Affected(affected) => Ok(affected),
+            RecordedResponse::Error(message) => Err(SqlExecutionError::Other {
+                err: message.into(),
+            }),
+        }
+    }
+
+    fn execute_sql_multi(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<ResultSet<'static>>, SqlExecutionError> {
+        // SQL executed through RecordingExecutor::execute_sql_multi is
+        // recorded as a whole. Otherwise its statements were recorded one by
+        // one, and a statement returned a result set if its rows were
+        // recorded, even none.
+        let recorded_whole = matches!(
+            self.interactions.as_slice().first(),
+            Some(interaction) if interaction.sql == sql
+        );
+        if recorded_whole {
+            return match self.replay(sql, &[])? {
+                RecordedResponse::Sets(sets) => Ok(sets.into_iter().map(replay_rows).collect()),
+                RecordedResponse::Rows(rows) => Ok(vec![replay_rows(rows)]),
+                RecordedResponse::Affected(_) => Ok(vec![]),
+                RecordedResponse::Error(message) => Err(SqlExecutionError::Other {
+                    err: message.into(),
+                }),
+            };
+        }
+
+        let mut sets = Vec::new();
+        for statement in split_statements(sql) {
+            match self.replay(statement, &[])? {
+                RecordedResponse::Rows(rows) => sets.push(replay_rows(rows)),
+                RecordedResponse::Sets(recorded) => {
+                    sets.extend(recorded.into_iter().map(replay_rows))
+                }
+                RecordedResponse::Affected(_) => {}
+                RecordedResponse::Error(message) => {
+                    return Err(SqlExecutionError::Other {
+                        err: message.into(),
+                    })
+                }
+            }
+        }
+
+        Ok(sets)
+    }
+
+    // The recorded responses already reflect the timeouts of the recording.
+    fn set_timeout(&mut self, _: Option<Duration>) -> Result<(), SqlExecutionError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::connections::Connection;
+use crate::executors::SqlExecutor;
+use crate::sql::SqlRow;
+use crate::sql::SqlValue;
+
+/// Runs SQL that is not known until runtime, panicking if it or any of its
+/// rows fail. This keeps setup statements in tests short.
+pub fn run<E>(connection: &mut Connection<E>, sql: &str, params: &[SqlValue])
+where
+    E: SqlExecutor,
+{
+    for row in connection.query_dynamic(sql, params).unwrap() {
+        row.unwrap();
+    }
+}
+
+/// Collects the rows returned by SQL that is not known until runtime,
+/// panicking if it or any of its rows fail.
+pub fn query<E>(connection: &mut Connection<E>, sql: &str, params: &[SqlValue]) -> Vec<SqlRow>
+where
+    E: SqlExecutor,
+{
+    connection
+        .query_dynamic(sql, params)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
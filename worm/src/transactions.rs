@@ -0,0 +1,211 @@
+//! Types for grouping scripts into atomic units of work.
+
+use crate::connections::Connection;
+use crate::errors::SqlExecutionError;
+use crate::executors::SqlExecutor;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use core::fmt::Result as FmtResult;
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+/// The isolation level of a transaction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IsolationLevel {
+    /// Statements can see uncommitted changes of other transactions.
+    ReadUncommitted,
+    /// Statements only see changes committed before they began.
+    ReadCommitted,
+    /// Statements only see changes committed before the transaction began.
+    RepeatableRead,
+    /// Transactions behave as if they were executed one after another.
+    Serializable,
+}
+
+impl Display for IsolationLevel {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            IsolationLevel::ReadUncommitted => f.write_str("READ UNCOMMITTED"),
+            IsolationLevel::ReadCommitted => f.write_str("READ COMMITTED"),
+            IsolationLevel::RepeatableRead => f.write_str("REPEATABLE READ"),
+            IsolationLevel::Serializable => f.write_str("SERIALIZABLE"),
+        }
+    }
+}
+
+/// The characteristics of a transaction. Any characteristic which is not set
+/// uses the default of the database.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TransactionOptions {
+    isolation_level: Option<IsolationLevel>,
+    read_only: Option<bool>,
+    deferrable: Option<bool>,
+}
+
+impl TransactionOptions {
+    /// Creates a set of options which uses the defaults of the database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the isolation level of the transaction.
+    pub fn isolation_level(mut self, isolation_level: IsolationLevel) -> Self {
+        self.isolation_level = Some(isolation_level);
+        self
+    }
+
+    /// Sets whether the transaction is limited to reading data.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = Some(read_only);
+        self
+    }
+
+    /// Sets whether a serializable, read only transaction may wait until it
+    /// can run without the possibility of a serialization failure.
+    pub fn deferrable(mut self, deferrable: bool) -> Self {
+        self.deferrable = Some(deferrable);
+        self
+    }
+
+    /// Gets the isolation level of the transaction.
+    pub fn get_isolation_level(&self) -> Option<IsolationLevel> {
+        self.isolation_level
+    }
+
+    /// Gets whether the transaction is limited to reading data.
+    pub fn get_read_only(&self) -> Option<bool> {
+        self.read_only
+    }
+
+    /// Gets whether the transaction is deferrable.
+    pub fn get_deferrable(&self) -> Option<bool> {
+        self.deferrable
+    }
+
+    /// Builds the standard SQL statement which begins a transaction with
+    /// these options.
+    pub fn begin_sql(&self) -> String {
+        let mut modes = vec![];
+        if let Some(isolation_level) = self.isolation_level {
+            modes.push(format!("ISOLATION LEVEL {}", isolation_level));
+        }
+
+        match self.read_only {
+            Some(true) => modes.push(String::from("READ ONLY")),
+            Some(false) => modes.push(String::from("READ WRITE")),
+            None => {}
+        }
+
+        match self.deferrable {
+            Some(true) => modes.push(String::from("DEFERRABLE")),
+            Some(false) => modes.push(String::from("NOT DEFERRABLE")),
+            None => {}
+        }
+
+        if modes.is_empty() {
+            String::from("START TRANSACTION")
+        } else {
+            format!("START TRANSACTION {}", modes.join(", "))
+        }
+    }
+}
+
+/// An open transaction on a [Connection]. Scripts are executed through the
+/// transaction in the same way as through the connection itself.
+///
+/// If the transaction is dropped without being committed, it is rolled back.
+/// Starting a transaction on a transaction creates a savepoint.
+pub struct Transaction<'c, E>
+where
+    E: SqlExecutor,
+{
+    connection: &'c mut Connection<E>,
+    savepoint: Option<String>,
+    finished: bool,
+}
+
+impl<'c, E> Transaction<'c, E>
+where
+    E: SqlExecutor,
+{
+    pub(crate) fn begin(
+        connection: &'c mut Connection<E>,
+        options: &TransactionOptions,
+    ) -> Result<Self, SqlExecutionError> {
+        let depth = connection.transaction_depth;
+        let savepoint = if depth == 0 {
+            connection.executor_mut().begin_transaction(options)?;
+            None
+        } else {
+            let name = format!("worm_savepoint_{}", depth);
+            connection.executor_mut().create_savepoint(&name)?;
+            Some(name)
+        };
+
+        connection.transaction_depth += 1;
+        Ok(Self {
+            connection,
+            savepoint,
+            finished: false,
+        })
+    }
+
+    /// Commits the transaction, or releases its savepoint if it is nested.
+    pub fn commit(mut self) -> Result<(), SqlExecutionError> {
+        self.finish(true)
+    }
+
+    /// Rolls back the transaction, or rolls back to its savepoint if it is
+    /// nested.
+    pub fn rollback(mut self) -> Result<(), SqlExecutionError> {
+        self.finish(false)
+    }
+
+    fn finish(&mut self, commit: bool) -> Result<(), SqlExecutionError> {
+        self.finished = true;
+        self.connection.transaction_depth -= 1;
+
+        let executor = self.connection.executor_mut();
+        match (&self.savepoint, commit) {
+            (None, true) => executor.commit_transaction(),
+            (None, false) => executor.rollback_transaction(),
+            (Some(name), true) => executor.release_savepoint(name),
+            (Some(name), false) => {
+                executor.rollback_to_savepoint(name)?;
+                executor.release_savepoint(name)
+            }
+        }
+    }
+}
+
+impl<'c, E> Deref for Transaction<'c, E>
+where
+    E: SqlExecutor,
+{
+    type Target = Connection<E>;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+    }
+}
+
+impl<'c, E> DerefMut for Transaction<'c, E>
+where
+    E: SqlExecutor,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection
+    }
+}
+
+impl<'c, E> Drop for Transaction<'c, E>
+where
+    E: SqlExecutor,
+{
+    fn drop(&mut self) {
+        if !self.finished {
+            // There is no way to report the error while dropping.
+            let _ = self.finish(false);
+        }
+    }
+}
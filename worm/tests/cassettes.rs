@@ -0,0 +1,298 @@
+//! Tests which record statements run against a MockExecutor into a cassette
+//! and replay them.
+
+mod common;
+
+use self::common::user;
+use std::error::Error;
+use std::path::PathBuf;
+use worm::connections::Connection;
+use worm::errors::CassetteError;
+use worm::errors::SqlExecutionError;
+use worm::errors::WormError;
+use worm::executors::SqlExecutor;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+use worm::testing::MockExecutor;
+use worm::testing::RecordingExecutor;
+use worm::testing::ReplayExecutor;
+use worm::Returns;
+use worm::Script;
+
+/// A cassette in the temporary directory which is deleted once the test has
+/// finished.
+struct TempCassette(PathBuf);
+
+impl TempCassette {
+    fn new(name: &str) -> Self {
+        let file_name = format!("worm-cassette-{}-{}.json", name, std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempCassette {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Runs the statements the tests record and replay.
+fn run_statements<E>(connection: &mut Connection<E>) -> (Vec<SqlRow>, u64, String)
+where
+    E: SqlExecutor,
+{
+    let rows = connection
+        .query_dynamic(
+            "SELECT name FROM users WHERE id = $1",
+            &[SqlValue::Signed64(1)],
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let affected = connection
+        .executor_mut()
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+
+    let error = connection
+        .executor_mut()
+        .execute_sql_affected("DROP TABLE users", &[])
+        .unwrap_err()
+        .to_string();
+
+    (rows, affected, error)
+}
+
+/// Records the statements into a cassette.
+fn record(cassette: &TempCassette) -> (Vec<SqlRow>, u64, String) {
+    let mock = MockExecutor::new();
+    mock.when_sql("SELECT name FROM users WHERE id = $1")
+        .returns(vec![user("ada"), user("grace")]);
+    mock.when_sql("DELETE FROM users").affects(2);
+    mock.when_sql("DROP TABLE users").fails("permission denied");
+
+    let mut connection = Connection::new(RecordingExecutor::new(mock, &cassette.0));
+    let recorded = connection.transaction(|tx| -> Result<_, WormError> { Ok(run_statements(tx)) });
+    recorded.unwrap()
+}
+
+fn cassette_error(err: &SqlExecutionError) -> &CassetteError {
+    match err {
+        SqlExecutionError::Other { err } => err
+            .downcast_ref::<CassetteError>()
+            .unwrap_or_else(|| panic!("{} is not a cassette error", err)),
+        other => panic!("{} is not a cassette error", other),
+    }
+}
+
+#[test]
+fn replays_recorded_statements() {
+    let cassette = TempCassette::new("replays");
+    let recorded = record(&cassette);
+    assert_eq!(recorded.0, [user("ada"), user("grace")]);
+    assert_eq!(recorded.1, 2);
+
+    let mut connection = Connection::new(ReplayExecutor::open(&cassette.0).unwrap());
+    let replayed = connection.transaction(|tx| -> Result<_, WormError> { Ok(run_statements(tx)) });
+    assert_eq!(replayed.unwrap(), recorded);
+    assert!(connection.executor_mut().is_finished());
+}
+
+#[test]
+fn writes_the_cassette_once_flushed_or_dropped() {
+    let cassette = TempCassette::new("flushes");
+    let mut recording = RecordingExecutor::new(MockExecutor::new(), &cassette.0);
+    recording
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+    assert!(!cassette.0.exists());
+
+    recording.flush().unwrap();
+    let replay = ReplayExecutor::open(&cassette.0).unwrap();
+    assert!(!replay.is_finished());
+
+    recording
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+    drop(recording);
+    let mut replay = ReplayExecutor::open(&cassette.0).unwrap();
+    replay
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+    replay
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+    assert!(replay.is_finished());
+}
+
+#[test]
+fn fails_when_a_statement_differs_from_the_recording() {
+    let cassette = TempCassette::new("mismatch");
+    record(&cassette);
+
+    let mut replay = ReplayExecutor::open(&cassette.0).unwrap();
+    replay.begin_transaction(&Default::default()).unwrap();
+    let err = replay
+        .execute_sql(
+            "SELECT name FROM users WHERE id = $1",
+            &[SqlValue::Signed64(2)],
+        )
+        .err()
+        .unwrap();
+
+    match cassette_error(&err) {
+        CassetteError::Mismatch {
+            expected_sql,
+            expected_params,
+            sql,
+            params,
+        } => {
+            assert_eq!(expected_sql, "SELECT name FROM users WHERE id = $1");
+            assert_eq!(expected_params, &[SqlValue::Signed64(1)]);
+            assert_eq!(sql, "SELECT name FROM users WHERE id = $1");
+            assert_eq!(params, &[SqlValue::Signed64(2)]);
+        }
+        other => panic!("expected a mismatch, but got {}", other),
+    }
+
+    let err = replay
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap_err();
+    assert!(matches!(
+        cassette_error(&err),
+        CassetteError::Mismatch { .. }
+    ));
+}
+
+#[test]
+fn fails_when_a_statement_was_not_recorded() {
+    let cassette = TempCassette::new("unrecorded");
+    {
+        let mut recording = RecordingExecutor::new(MockExecutor::new(), &cassette.0);
+        recording
+            .execute_sql_affected("DELETE FROM users", &[])
+            .unwrap();
+    }
+
+    let mut replay = ReplayExecutor::open(&cassette.0).unwrap();
+    replay
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap();
+    let err = replay
+        .execute_sql_affected("DELETE FROM users", &[])
+        .unwrap_err();
+    match cassette_error(&err) {
+        CassetteError::Unrecorded { sql } => assert_eq!(sql, "DELETE FROM users"),
+        other => panic!("expected an unrecorded statement, but got {}", other),
+    }
+}
+
+struct DeleteUser {
+    id: i64,
+}
+
+impl Script for DeleteUser {
+    type Output = ();
+
+    const RETURNS: Returns = Returns::None;
+
+    fn compile(self) -> String {
+        format!("DELETE FROM users WHERE id = {}", self.id)
+    }
+}
+
+/// What running batches and SQL with several statements returned.
+type BatchResults = (Vec<Result<u64, String>>, Vec<u64>, String, Vec<Vec<SqlRow>>);
+
+/// Runs batches and SQL with several statements, which a recording passes to
+/// the executor it wraps whole.
+fn run_batches<E>(connection: &mut Connection<E>) -> BatchResults
+where
+    E: SqlExecutor,
+{
+    let batch = connection
+        .execute_batch((1..=2).map(|id| DeleteUser { id }))
+        .unwrap()
+        .into_iter()
+        .map(|result| result.map_err(|err| err.to_string()))
+        .collect();
+
+    let atomic = connection
+        .execute_batch_atomic((3..=4).map(|id| DeleteUser { id }))
+        .unwrap();
+
+    let failed = connection
+        .execute_batch_atomic((1..=3).map(|id| DeleteUser { id }))
+        .unwrap_err()
+        .to_string();
+
+    let sets = connection
+        .executor_mut()
+        .execute_sql_multi("SELECT name FROM users; DELETE FROM users; SELECT name FROM people")
+        .unwrap()
+        .into_iter()
+        .map(|result_set| result_set.collect::<Result<Vec<_>, _>>().unwrap())
+        .collect();
+
+    (batch, atomic, failed, sets)
+}
+
+#[test]
+fn records_batches_and_several_statements_through_the_wrapped_executor() {
+    let cassette = TempCassette::new("batches");
+    let mock = MockExecutor::new();
+    mock.when_sql("DELETE FROM users WHERE id = 2")
+        .fails("permission denied");
+    mock.when_sql("DELETE FROM users WHERE id = 3").affects(1);
+    mock.when_sql("DELETE FROM users WHERE id = 4").affects(2);
+    mock.when_sql("SELECT name FROM users")
+        .returns(vec![user("ada")]);
+    mock.when_sql("SELECT name FROM people").returns(vec![]);
+
+    let mut connection = Connection::new(RecordingExecutor::new(mock.clone(), &cassette.0));
+    let recorded = connection.transaction(|tx| -> Result<_, WormError> { Ok(run_batches(tx)) });
+    let recorded = recorded.unwrap();
+    drop(connection);
+
+    assert_eq!(recorded.0[0], Ok(0));
+    assert!(recorded.0[1]
+        .as_ref()
+        .unwrap_err()
+        .ends_with("permission denied"));
+    assert_eq!(recorded.1, [1, 2]);
+    assert_eq!(recorded.3, [vec![user("ada")], vec![]]);
+
+    // The wrapped executor ran every statement of each batch as part of its
+    // script, which it only does when it is passed the batch whole.
+    assert_eq!(mock.executions_of::<DeleteUser>().len(), 2 + 2 + 2);
+
+    let mut connection = Connection::new(ReplayExecutor::open(&cassette.0).unwrap());
+    let replayed = connection.transaction(|tx| -> Result<_, WormError> { Ok(run_batches(tx)) });
+    assert_eq!(replayed.unwrap(), recorded);
+    assert!(connection.executor_mut().is_finished());
+}
+
+#[test]
+fn reports_why_an_executor_could_not_connect() {
+    let err = match ReplayExecutor::connect("/nonexistent/cassette.json") {
+        Err(err) => err,
+        Ok(_) => panic!("expected the missing cassette to be reported"),
+    };
+    let source = err.source().unwrap();
+    assert!(
+        source.downcast_ref::<CassetteError>().is_some(),
+        "{}",
+        source
+    );
+
+    // This is the only test which reads the variable.
+    std::env::remove_var("WORM_CASSETTE");
+    let err = match RecordingExecutor::<MockExecutor>::connect("mock") {
+        Err(err) => err,
+        Ok(_) => panic!("expected the missing variable to be reported"),
+    };
+    assert!(err.source().unwrap().to_string().contains("WORM_CASSETTE"));
+}
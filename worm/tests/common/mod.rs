@@ -0,0 +1,13 @@
+//! Fixtures shared by the integration tests.
+
+use std::sync::Arc;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+
+/// A row of a `users` table with only a name.
+pub fn user(name: &str) -> SqlRow {
+    SqlRow::new(
+        Arc::from(vec![String::from("name")]),
+        vec![SqlValue::String(name.to_owned())],
+    )
+}
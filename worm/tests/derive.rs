@@ -0,0 +1,108 @@
+//! Tests of the rows which `#[derive(SqlResult)]` converts into structs.
+
+use std::sync::Arc;
+use worm::errors::RowConversionError;
+use worm::sql::SqlResult;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+
+#[derive(Debug, PartialEq, SqlResult)]
+struct User {
+    id: i64,
+    name: String,
+    nickname: Option<String>,
+}
+
+#[derive(Debug, PartialEq, SqlResult)]
+struct Keyword {
+    r#type: String,
+}
+
+fn row(columns: &[&str], values: Vec<SqlValue>) -> SqlRow {
+    let columns = columns
+        .iter()
+        .map(|column| column.to_string())
+        .collect::<Vec<_>>();
+    SqlRow::new(Arc::from(columns), values)
+}
+
+#[test]
+fn reads_fields_by_column_name() {
+    let row = row(
+        &["nickname", "name", "created", "id"],
+        vec![
+            SqlValue::Null,
+            SqlValue::String("ada".into()),
+            SqlValue::String("yesterday".into()),
+            SqlValue::Signed64(1),
+        ],
+    );
+
+    let user = User::from_row(row).unwrap();
+    assert_eq!(
+        user,
+        User {
+            id: 1,
+            name: String::from("ada"),
+            nickname: None,
+        }
+    );
+}
+
+#[test]
+fn reads_raw_identifiers_by_their_name() {
+    let row = row(&["type"], vec![SqlValue::String("select".into())]);
+    assert_eq!(
+        Keyword::from_row(row).unwrap(),
+        Keyword {
+            r#type: String::from("select"),
+        }
+    );
+}
+
+#[test]
+fn fails_when_a_column_is_missing() {
+    let row = row(
+        &["id", "nickname"],
+        vec![SqlValue::Signed64(1), SqlValue::Null],
+    );
+
+    match User::from_row(row) {
+        Err(RowConversionError::ColumnNotFound { column }) => assert_eq!(column, "name"),
+        other => panic!("expected a missing column, but got {:?}", other),
+    }
+}
+
+#[test]
+fn falls_back_to_positions_without_column_names() {
+    let row = row(
+        &[],
+        vec![
+            SqlValue::Signed64(2),
+            SqlValue::String("grace".into()),
+            SqlValue::String("amazing".into()),
+        ],
+    );
+
+    let user = User::from_row(row).unwrap();
+    assert_eq!(
+        user,
+        User {
+            id: 2,
+            name: String::from("grace"),
+            nickname: Some(String::from("amazing")),
+        }
+    );
+}
+
+#[test]
+fn fails_when_a_position_is_missing() {
+    let row = row(&[], vec![SqlValue::Signed64(2)]);
+
+    match User::from_row(row) {
+        Err(RowConversionError::MissingFieldValue { field_name }) => {
+            assert_eq!(field_name, "name")
+        }
+        other => panic!("expected a missing value, but got {:?}", other),
+    }
+}
@@ -0,0 +1,327 @@
+//! Tests of the hooks an Intercepted executor calls around its statements and
+//! of the layers built on them.
+
+mod common;
+
+use self::common::user;
+use std::sync::Arc;
+use std::sync::Mutex;
+use worm::connections::Connection;
+use worm::errors::ConnectionError;
+use worm::errors::RowConversionError;
+use worm::errors::SqlExecutionError;
+use worm::executors::ResultSet;
+use worm::executors::SqlExecutor;
+use worm::layers::Intercepted;
+use worm::layers::Interceptor;
+use worm::layers::Outcome;
+use worm::layers::Statement;
+use worm::layers::TimingLayer;
+use worm::layers::Timings;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+use worm::testing::MockExecutor;
+use worm::Returns;
+use worm::Script;
+use worm::ScriptInfo;
+
+/// What the interceptor saw when a statement finished.
+#[derive(Debug, PartialEq)]
+struct Finished {
+    script: Option<&'static str>,
+    sql: String,
+    rows: u64,
+    error: Option<String>,
+}
+
+/// Rewrites `users` to `people` and records each statement once it finishes.
+#[derive(Clone, Default)]
+struct Recorder {
+    finished: Arc<Mutex<Vec<Finished>>>,
+}
+
+impl Recorder {
+    fn finished(&self) -> Vec<Finished> {
+        std::mem::take(&mut *self.finished.lock().unwrap())
+    }
+}
+
+impl Interceptor for Recorder {
+    fn before(&self, statement: &mut Statement<'_>) {
+        let sql = statement.sql().replace("users", "people");
+        statement.set_sql(sql);
+    }
+
+    fn after(&self, statement: &Statement<'_>, outcome: &Outcome<'_>) {
+        self.finished.lock().unwrap().push(Finished {
+            script: statement.script_name(),
+            sql: statement.sql().to_owned(),
+            rows: outcome.rows(),
+            error: outcome.error().map(ToString::to_string),
+        });
+    }
+}
+
+struct DeleteUser {
+    id: i64,
+}
+
+impl Script for DeleteUser {
+    type Output = ();
+
+    const RETURNS: Returns = Returns::None;
+
+    fn compile(self) -> String {
+        format!("DELETE FROM users WHERE id = {}", self.id)
+    }
+}
+
+fn finished(script: Option<&'static str>, sql: &str, rows: u64, error: Option<&str>) -> Finished {
+    Finished {
+        script,
+        sql: sql.to_owned(),
+        rows,
+        error: error.map(|message| format!("There was a problem executing the SQL: {}", message)),
+    }
+}
+
+fn connect() -> (
+    MockExecutor,
+    Recorder,
+    Connection<Intercepted<MockExecutor, Recorder>>,
+) {
+    let mock = MockExecutor::new();
+    let recorder = Recorder::default();
+    let connection = Connection::new(Intercepted::new(mock.clone(), recorder.clone()));
+    (mock, recorder, connection)
+}
+
+#[test]
+fn executes_the_rewritten_sql() {
+    let (mock, recorder, mut connection) = connect();
+    mock.when_sql("SELECT name FROM people")
+        .returns(vec![user("ada"), user("grace")]);
+
+    let rows = connection
+        .query_dynamic("SELECT name FROM users", &[])
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows, [user("ada"), user("grace")]);
+    mock.assert_executed_sql("SELECT name FROM people");
+    assert_eq!(
+        recorder.finished(),
+        [finished(None, "SELECT name FROM people", 2, None)]
+    );
+}
+
+#[test]
+fn finishes_statements_once_their_rows_are_dropped() {
+    let (mock, recorder, mut connection) = connect();
+    mock.when_sql("SELECT name FROM people")
+        .returns(vec![user("ada"), user("grace")]);
+
+    let mut rows = connection
+        .query_dynamic("SELECT name FROM users", &[])
+        .unwrap();
+    rows.next().unwrap().unwrap();
+    assert!(recorder.finished().is_empty());
+
+    drop(rows);
+    assert_eq!(
+        recorder.finished(),
+        [finished(None, "SELECT name FROM people", 1, None)]
+    );
+}
+
+#[test]
+fn reports_the_rows_affected_and_errors() {
+    let (mock, recorder, mut connection) = connect();
+    mock.when_sql("DELETE FROM people WHERE id = 1").affects(3);
+    mock.when_sql("DELETE FROM people WHERE id = 2")
+        .fails("permission denied");
+
+    let script = Some(ScriptInfo::of::<DeleteUser>().name());
+    assert_eq!(
+        connection.execute_affected(DeleteUser { id: 1 }).unwrap(),
+        3
+    );
+    assert!(connection.execute_affected(DeleteUser { id: 2 }).is_err());
+    assert_eq!(
+        recorder.finished(),
+        [
+            finished(script, "DELETE FROM people WHERE id = 1", 3, None),
+            finished(
+                script,
+                "DELETE FROM people WHERE id = 2",
+                0,
+                Some("permission denied")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn passes_batches_through_whole() {
+    let (mock, recorder, mut connection) = connect();
+    mock.when_sql("DELETE FROM people WHERE id = 1").affects(1);
+    mock.when_sql("DELETE FROM people WHERE id = 2")
+        .fails("permission denied");
+
+    let users = (1..=2).map(|id| DeleteUser { id });
+    let results = connection.execute_batch(users).unwrap();
+    assert_eq!(*results[0].as_ref().unwrap(), 1);
+    assert!(results[1].is_err());
+
+    // The mock sees the rewritten statements once each, attributed to the
+    // script.
+    let executions = mock.executions_of::<DeleteUser>();
+    assert_eq!(executions.len(), 2);
+    assert_eq!(executions[0].sql(), "DELETE FROM people WHERE id = 1");
+
+    let script = Some(ScriptInfo::of::<DeleteUser>().name());
+    assert_eq!(
+        recorder.finished(),
+        [
+            finished(script, "DELETE FROM people WHERE id = 1", 1, None),
+            finished(
+                script,
+                "DELETE FROM people WHERE id = 2",
+                0,
+                Some("permission denied")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn fails_every_statement_of_a_failed_atomic_batch() {
+    let (mock, recorder, mut connection) = connect();
+    mock.when_sql("DELETE FROM people WHERE id = 2")
+        .fails("permission denied");
+
+    let users = (1..=3).map(|id| DeleteUser { id });
+    assert!(connection.execute_batch_atomic(users).is_err());
+
+    // The batch stops at the statement which failed.
+    assert_eq!(mock.executions_of::<DeleteUser>().len(), 2);
+    let script = Some(ScriptInfo::of::<DeleteUser>().name());
+    let failed = |sql| finished(script, sql, 0, Some("permission denied"));
+    assert_eq!(
+        recorder.finished(),
+        [
+            failed("DELETE FROM people WHERE id = 1"),
+            failed("DELETE FROM people WHERE id = 2"),
+            failed("DELETE FROM people WHERE id = 3"),
+        ]
+    );
+}
+
+/// An executor which returns a row and then a row which cannot be read.
+struct BrokenRows;
+
+impl SqlExecutor for BrokenRows {
+    fn connect(_: &str) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self)
+    }
+
+    fn execute_sql<'a>(
+        &'a mut self,
+        _: &str,
+        _: &[SqlValue],
+    ) -> Result<ResultSet<'a>, SqlExecutionError> {
+        let rows = vec![
+            Ok(user("ada")),
+            Err(RowConversionError::ColumnNotFound {
+                column: String::from("name"),
+            }),
+        ];
+        Ok(ResultSet::new(
+            Arc::from(vec![]),
+            Box::new(rows.into_iter()),
+        ))
+    }
+
+    fn execute_sql_affected(&mut self, _: &str, _: &[SqlValue]) -> Result<u64, SqlExecutionError> {
+        Ok(0)
+    }
+}
+
+#[test]
+fn fails_statements_whose_rows_cannot_be_read() {
+    let recorder = Recorder::default();
+    let mut connection = Connection::new(Intercepted::new(BrokenRows, recorder.clone()));
+
+    let rows = connection
+        .query_dynamic("SELECT name FROM users", &[])
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert!(rows[0].is_ok());
+    assert!(rows[1].is_err());
+    assert_eq!(
+        recorder.finished(),
+        [finished(
+            None,
+            "SELECT name FROM people",
+            0,
+            Some("The row has no column 'name'")
+        )]
+    );
+}
+
+struct CountUsers;
+
+impl Script for CountUsers {
+    type Output = SqlRow;
+
+    const RETURNS: Returns = Returns::One;
+
+    fn compile(self) -> String {
+        String::from("SELECT count(*) FROM users")
+    }
+}
+
+#[test]
+fn times_the_statements_of_each_script() {
+    let mock = MockExecutor::new();
+    let timing = TimingLayer::new();
+    let mut connection = Connection::new(Intercepted::new(mock.clone(), timing.clone()));
+    mock.when_sql("DELETE FROM users WHERE id = 1").affects(3);
+    mock.when_sql("DELETE FROM users WHERE id = 2")
+        .fails("permission denied");
+    mock.when_sql("DELETE FROM users WHERE id = 3").affects(1);
+    mock.when_sql("SELECT name FROM users")
+        .returns(vec![user("ada"), user("grace")]);
+
+    for id in 1..=3 {
+        let _ = connection.execute_affected(DeleteUser { id });
+    }
+    let rows = connection
+        .query_dynamic("SELECT name FROM users", &[])
+        .unwrap()
+        .count();
+    assert_eq!(rows, 2);
+
+    let deletes = timing.timings_of::<DeleteUser>();
+    assert_eq!(deletes.count(), 3);
+    assert_eq!(deletes.errors(), 1);
+    assert_eq!(deletes.rows(), 4);
+    assert!(deletes.max() <= deletes.total());
+    assert!(deletes.mean() <= deletes.max());
+
+    // Statements which were not compiled from a script are timed together,
+    // and scripts which never ran have no timings.
+    let dynamic = timing.timings()[&None];
+    assert_eq!(
+        (dynamic.count(), dynamic.errors(), dynamic.rows()),
+        (1, 0, 2)
+    );
+    assert_eq!(timing.timings_of::<CountUsers>(), Timings::default());
+    assert_eq!(timing.timings().len(), 2);
+
+    timing.reset();
+    assert!(timing.timings().is_empty());
+}
@@ -0,0 +1,158 @@
+//! Tests of the messages the LoggingLayer logs for each statement.
+
+mod common;
+
+use self::common::user;
+use core::time::Duration;
+use log::Level;
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use log::Record;
+use std::cell::RefCell;
+use worm::connections::Connection;
+use worm::layers::Intercepted;
+use worm::layers::LoggingLayer;
+use worm::sql::SqlValue;
+use worm::testing::MockExecutor;
+use worm::Returns;
+use worm::Script;
+use worm::ScriptInfo;
+
+thread_local! {
+    /// The messages logged by the test running on this thread, so that tests
+    /// running alongside it do not see them.
+    static LOGGED: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A logger which keeps the messages of each thread.
+struct Capture;
+
+impl Log for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let message = (record.level(), record.args().to_string());
+        LOGGED.with(|logged| logged.borrow_mut().push(message));
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture;
+
+struct DeleteUser {
+    id: i64,
+}
+
+impl Script for DeleteUser {
+    type Output = ();
+
+    const RETURNS: Returns = Returns::None;
+
+    fn compile(self) -> String {
+        format!("DELETE FROM users WHERE id = {}", self.id)
+    }
+}
+
+/// Connects to a mock through the layer.
+fn connect(layer: LoggingLayer) -> Connection<Intercepted<MockExecutor, LoggingLayer>> {
+    // Only the first test to start installs the logger.
+    let _ = log::set_logger(&CAPTURE);
+    log::set_max_level(LevelFilter::Trace);
+
+    let mock = MockExecutor::new();
+    mock.when_sql("SELECT name FROM users WHERE name = $1")
+        .returns(vec![user("ada"), user("grace")]);
+    mock.when_sql("DELETE FROM users WHERE id = 1")
+        .fails("permission denied");
+    Connection::new(Intercepted::new(mock, layer))
+}
+
+/// Takes the messages logged by the current test so far.
+fn logged() -> Vec<(Level, String)> {
+    LOGGED.with(|logged| std::mem::take(&mut *logged.borrow_mut()))
+}
+
+fn select_users(connection: &mut Connection<Intercepted<MockExecutor, LoggingLayer>>) {
+    let params = [SqlValue::String(String::from("ada"))];
+    let rows = connection
+        .query_dynamic("SELECT name FROM users WHERE name = $1", &params)
+        .unwrap()
+        .count();
+    assert_eq!(rows, 2);
+}
+
+#[test]
+fn logs_statements_at_the_level_of_the_layer() {
+    let mut connection = connect(LoggingLayer::new().level(Level::Info));
+    select_users(&mut connection);
+
+    let logged = logged();
+    assert_eq!(logged.len(), 1);
+    let (level, message) = &logged[0];
+    assert_eq!(*level, Level::Info);
+    assert!(message.starts_with("dynamic SQL took "), "{}", message);
+    assert!(
+        message.ends_with(" for 2 rows: SELECT name FROM users WHERE name = $1"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn logs_parameters_only_when_asked_to() {
+    let mut connection = connect(LoggingLayer::new());
+    select_users(&mut connection);
+    let (level, message) = logged().remove(0);
+    assert_eq!(level, Level::Debug);
+    assert!(!message.contains("ada"), "{}", message);
+
+    let mut connection = connect(LoggingLayer::new().params(true));
+    select_users(&mut connection);
+    let (_, message) = logged().remove(0);
+    assert!(
+        message.ends_with(r#"WHERE name = $1 with [String("ada")]"#),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn logs_failed_statements_as_errors() {
+    let mut connection = connect(LoggingLayer::new());
+    assert!(connection.execute_affected(DeleteUser { id: 1 }).is_err());
+
+    let logged = logged();
+    assert_eq!(logged.len(), 1);
+    let (level, message) = &logged[0];
+    assert_eq!(*level, Level::Error);
+    let script = ScriptInfo::of::<DeleteUser>().name();
+    assert!(
+        message.starts_with(&format!("{} failed after ", script)),
+        "{}",
+        message
+    );
+    assert!(
+        message.ends_with(
+            ": DELETE FROM users WHERE id = 1: \
+                There was a problem executing the SQL: permission denied"
+        ),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn logs_slow_statements_as_warnings() {
+    let mut connection = connect(LoggingLayer::new().slow_threshold(Duration::from_secs(60)));
+    select_users(&mut connection);
+    assert_eq!(logged()[0].0, Level::Debug);
+
+    // Every statement takes longer than no time at all.
+    let mut connection = connect(LoggingLayer::new().slow_threshold(Duration::default()));
+    select_users(&mut connection);
+    assert_eq!(logged()[0].0, Level::Warn);
+}
@@ -0,0 +1,214 @@
+//! Tests of the responses and assertions of the MockExecutor.
+
+mod common;
+
+use self::common::user;
+use worm::connections::Connection;
+use worm::errors::CardinalityError;
+use worm::errors::WormError;
+use worm::executors::SqlExecutor;
+use worm::sql::SqlRow;
+use worm::sql::SqlValue;
+use worm::testing::MockExecutor;
+use worm::Returns;
+use worm::Script;
+use worm::ScriptInfo;
+
+struct FindUser {
+    id: i64,
+}
+
+impl Script for FindUser {
+    type Output = SqlRow;
+
+    const RETURNS: Returns = Returns::One;
+
+    fn compile(self) -> String {
+        format!("SELECT name FROM users WHERE id = {}", self.id)
+    }
+}
+
+struct DeleteUsers;
+
+impl Script for DeleteUsers {
+    type Output = ();
+
+    const RETURNS: Returns = Returns::None;
+
+    fn compile(self) -> String {
+        String::from("DELETE FROM users")
+    }
+}
+
+fn connect() -> (MockExecutor, Connection<MockExecutor>) {
+    let mock = MockExecutor::new();
+    (mock.clone(), Connection::new(mock))
+}
+
+#[test]
+fn returns_no_rows_by_default() {
+    let (mock, mut connection) = connect();
+    let rows = connection.query_dynamic("SELECT 1", &[]).unwrap().count();
+    assert_eq!(rows, 0);
+    assert_eq!(
+        connection
+            .executor_mut()
+            .execute_sql_affected("DELETE FROM users", &[])
+            .unwrap(),
+        0
+    );
+    assert_eq!(mock.executions().len(), 2);
+}
+
+#[test]
+fn matches_responses_by_script() {
+    let (mock, mut connection) = connect();
+    mock.when::<FindUser>().returns(vec![user("ada")]);
+
+    let row = connection.fetch_one(FindUser { id: 1 }).unwrap();
+    assert_eq!(row.try_get::<String, _>("name").unwrap(), "ada");
+
+    // SQL which was not compiled from the script does not match.
+    let rows = connection
+        .query_dynamic("SELECT name FROM users WHERE id = 1", &[])
+        .unwrap()
+        .count();
+    assert_eq!(rows, 0);
+}
+
+#[test]
+fn matches_responses_by_sql() {
+    let (mock, mut connection) = connect();
+    mock.when_sql("SELECT name FROM users WHERE id = $1")
+        .returns(vec![user("ada"), user("grace")]);
+    mock.when_sql("DELETE FROM users").affects(3);
+
+    let rows = connection
+        .query_dynamic(
+            "SELECT name FROM users WHERE id = $1",
+            &[SqlValue::Signed64(1)],
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows, [user("ada"), user("grace")]);
+
+    // A script matches on its compiled SQL as well.
+    assert_eq!(connection.execute_affected(DeleteUsers).unwrap(), 3);
+}
+
+#[test]
+fn uses_the_most_recent_matching_response() {
+    let (mock, mut connection) = connect();
+    mock.when::<FindUser>().returns(vec![user("ada")]);
+    mock.when::<FindUser>().returns(vec![]);
+
+    match connection.fetch_one(FindUser { id: 1 }) {
+        Err(WormError::CardinalityError(CardinalityError::NoRows)) => {}
+        other => panic!("expected no rows, but got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn fails_with_the_programmed_message() {
+    let (mock, mut connection) = connect();
+    mock.when::<DeleteUsers>().fails("permission denied");
+
+    match connection.execute_affected(DeleteUsers) {
+        Err(WormError::SqlExecutionError(err)) => {
+            assert!(err.to_string().contains("permission denied"), "{}", err)
+        }
+        other => panic!("expected an execution error, but got {:?}", other.is_ok()),
+    }
+}
+
+#[test]
+fn records_executions() {
+    let (mock, mut connection) = connect();
+    connection.fetch_optional(FindUser { id: 7 }).unwrap();
+    connection
+        .query_dynamic("SELECT $1", &[SqlValue::Signed64(1)])
+        .unwrap()
+        .count();
+
+    let executions = mock.executions();
+    assert_eq!(executions.len(), 2);
+    assert_eq!(executions[0].sql(), "SELECT name FROM users WHERE id = 7");
+    assert_eq!(executions[0].script(), Some(&ScriptInfo::of::<FindUser>()));
+    assert_eq!(executions[1].sql(), "SELECT $1");
+    assert_eq!(executions[1].params(), [SqlValue::Signed64(1)]);
+    assert_eq!(executions[1].script(), None);
+
+    assert_eq!(mock.executions_of::<FindUser>().len(), 1);
+    assert!(mock.executions_of::<DeleteUsers>().is_empty());
+
+    mock.clear();
+    assert!(mock.executions().is_empty());
+}
+
+#[test]
+fn asserts_executions() {
+    let (mock, mut connection) = connect();
+    connection.execute_affected(DeleteUsers).unwrap();
+
+    mock.assert_executed::<DeleteUsers>();
+    mock.assert_not_executed::<FindUser>();
+    mock.assert_executed_sql("DELETE FROM users");
+}
+
+#[test]
+#[should_panic(expected = "to be executed")]
+fn panics_if_a_script_was_not_executed() {
+    let (mock, _) = connect();
+    mock.assert_executed::<DeleteUsers>();
+}
+
+#[test]
+#[should_panic(expected = "not to be executed")]
+fn panics_if_a_script_was_executed() {
+    let (mock, mut connection) = connect();
+    connection.execute_affected(DeleteUsers).unwrap();
+    mock.assert_not_executed::<DeleteUsers>();
+}
+
+#[test]
+#[should_panic(expected = "\"SELECT 1\" to be executed")]
+fn panics_if_sql_was_not_executed() {
+    let (mock, _) = connect();
+    mock.assert_executed_sql("SELECT 1");
+}
+
+#[test]
+fn attributes_every_statement_of_a_batch_to_its_script() {
+    let (mock, mut connection) = connect();
+    mock.when::<DeleteUsers>().affects(2);
+
+    let results = connection
+        .execute_batch(vec![DeleteUsers, DeleteUsers])
+        .unwrap();
+    let affected = results.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(affected, [2, 2]);
+    assert_eq!(mock.executions_of::<DeleteUsers>().len(), 2);
+}
+
+struct FindUsersAndCount;
+
+impl Script for FindUsersAndCount {
+    type Output = (SqlRow, SqlRow);
+
+    fn compile(self) -> String {
+        String::from("SELECT name FROM users; SELECT COUNT(*) FROM users")
+    }
+}
+
+#[test]
+fn rejects_executing_several_result_sets_as_one() {
+    let (mock, mut connection) = connect();
+    match connection.fetch_all(FindUsersAndCount) {
+        Err(WormError::CardinalityError(CardinalityError::MultipleResultSets { expected })) => {
+            assert_eq!(expected, 2)
+        }
+        other => panic!("expected a cardinality error, but got {:?}", other.is_ok()),
+    }
+    assert!(mock.executions().is_empty());
+}
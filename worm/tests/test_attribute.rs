@@ -0,0 +1,29 @@
+//! Tests which run through the `#[worm::test]` attribute against a
+//! MockExecutor, which records the statements used to isolate the test.
+
+use worm::connections::Connection;
+use worm::testing::MockExecutor;
+
+// The mock ignores the connection string, so any variable which cargo sets
+// while running the tests will do.
+#[worm::test(env = "CARGO_MANIFEST_DIR")]
+fn runs_within_a_transaction(connection: &mut Connection<MockExecutor>) {
+    let executions = connection.executor_mut().executions();
+    let sql = executions
+        .iter()
+        .map(|execution| execution.sql())
+        .collect::<Vec<_>>();
+    assert_eq!(sql, ["START TRANSACTION"]);
+}
+
+#[worm::test(isolation = "schema", env = "CARGO_MANIFEST_DIR")]
+fn runs_within_a_schema(connection: &mut Connection<MockExecutor>) -> Result<(), String> {
+    let executions = connection.executor_mut().executions();
+    match executions.first().map(|execution| execution.sql()) {
+        Some(sql) if sql.starts_with("CREATE SCHEMA") => Ok(()),
+        other => Err(format!(
+            "expected the schema to be created, not {:?}",
+            other
+        )),
+    }
+}